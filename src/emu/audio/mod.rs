@@ -0,0 +1,24 @@
+#[cfg(feature = "sdl")]
+mod output;
+pub mod resampler;
+#[cfg(feature = "sdl")]
+mod ring;
+pub mod wav;
+
+#[cfg(feature = "sdl")]
+pub(crate) use self::output::AudioOutput;
+
+use super::options::Options;
+use crate::gameboy::Mixer;
+
+// the apu is sampled at this rate before resampling to the device, high enough that averaging
+// it down from the gameboy's clock only touches frequencies far above hearing
+pub const EMULATED_RATE: u32 = 131072;
+
+// the mixer a run starts with, the window's hotkeys change it from there. the high pass is
+// applied to stems too, so a recording's stems add up to its mix either way
+pub fn new_mixer(options: &Options) -> Mixer {
+    let mut mixer = Mixer::new();
+    mixer.high_pass = options.high_pass;
+    mixer
+}
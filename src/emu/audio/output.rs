@@ -0,0 +1,143 @@
+use std::{thread, time::Duration};
+
+use sdl2::{
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+    Sdl,
+};
+
+use super::{
+    resampler::Resampler,
+    ring::{ring_buffer, Consumer, Producer},
+    EMULATED_RATE,
+};
+use crate::gameboy::Sample;
+
+const DEVICE_RATE: i32 = 48000;
+// samples the device asks for at a time
+const DEVICE_SAMPLES: u16 = 512;
+// how much audio is queued ahead of the device, the ring holds twice this so rate control
+// has room either side
+const LATENCY: Duration = Duration::from_millis(60);
+// how often to check the queue while waiting on the device
+const WAIT_INTERVAL: Duration = Duration::from_millis(1);
+// how far rate control may stretch or squeeze the audio, too little to hear as pitch
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+
+// plays the apu's samples through sdl. the emulator resamples and queues each frame's worth,
+// sdl's audio thread takes them off the other end of the ring
+pub(crate) struct AudioOutput {
+    device: AudioDevice<Playback>,
+    producer: Producer,
+    resampler: Resampler,
+    // the emulator waits on the device instead of the clock, see wait
+    master: bool,
+}
+
+impl AudioOutput {
+    pub fn new(sdl: &Sdl, master: bool) -> Self {
+        let audio = sdl.audio().expect("failed to init sdl2 audio");
+        let desired = AudioSpecDesired {
+            freq: Some(DEVICE_RATE),
+            channels: Some(2),
+            samples: Some(DEVICE_SAMPLES),
+        };
+        let mut producer = None;
+        let device = audio
+            .open_playback(None, &desired, |spec| {
+                let latency = spec.freq as usize * LATENCY.as_millis() as usize / 1000;
+                let (ring_producer, consumer) = ring_buffer(latency * 2);
+                producer = Some(ring_producer);
+                Playback {
+                    consumer,
+                    last: Sample::default(),
+                }
+            })
+            .expect("failed to open the audio device");
+        let device_rate = device.spec().freq as u32;
+
+        let output = Self {
+            device,
+            producer: producer.unwrap(),
+            resampler: Resampler::new(EMULATED_RATE, device_rate),
+            master,
+        };
+        output.fill_to_latency();
+        output.device.resume();
+        output
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.master
+    }
+
+    pub fn queue(&mut self, samples: &[Sample]) {
+        // after running dry, eg while the window was dragged, start again from the full
+        // latency rather than crawling back up to it
+        if self.producer.len() == 0 {
+            self.fill_to_latency();
+        }
+        if !self.master {
+            let fill = self.producer.len() as f64 / self.producer.capacity() as f64;
+            self.resampler.set_adjustment(rate_adjustment(fill));
+        }
+        let resampled = self.resampler.process(samples);
+        self.producer.push(&resampled);
+    }
+
+    // audio as the master clock, the next frame starts once the device has played the queue
+    // down to the latency. the emulator then runs at whatever rate the device really plays
+    pub fn wait(&self) {
+        while self.producer.len() > self.latency() {
+            thread::sleep(WAIT_INTERVAL);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.producer.capacity() / 2
+    }
+
+    fn fill_to_latency(&self) {
+        let missing = self.latency().saturating_sub(self.producer.len());
+        self.producer.push(&vec![Sample::default(); missing]);
+    }
+}
+
+struct Playback {
+    consumer: Consumer,
+    last: Sample,
+}
+
+impl AudioCallback for Playback {
+    type Channel = f32;
+
+    // running dry repeats the last sample, which doesn't click like dropping to silence
+    fn callback(&mut self, out: &mut [f32]) {
+        for frame in out.chunks_exact_mut(2) {
+            if let Some(sample) = self.consumer.pop() {
+                self.last = sample;
+            }
+            frame[0] = self.last.left;
+            frame[1] = self.last.right;
+        }
+    }
+}
+
+// dynamic rate control. the emulator and the audio device run off different clocks, so the
+// buffer between them slowly fills or drains. running the resampler slightly fast when the
+// buffer is more than half full and slightly slow when it's less keeps it near half
+fn rate_adjustment(fill: f64) -> f64 {
+    MAX_RATE_ADJUSTMENT * (2.0 * fill.clamp(0.0, 1.0) - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_adjustment() {
+        assert_eq!(rate_adjustment(0.5), 0.0);
+        assert_eq!(rate_adjustment(1.0), MAX_RATE_ADJUSTMENT);
+        assert_eq!(rate_adjustment(0.0), -MAX_RATE_ADJUSTMENT);
+        assert_eq!(rate_adjustment(2.0), MAX_RATE_ADJUSTMENT);
+    }
+}
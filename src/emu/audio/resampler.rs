@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+
+use crate::gameboy::Sample;
+
+// zero crossings of the sinc kept either side of each output sample
+const ZERO_CROSSINGS: f64 = 16.0;
+// the kernel is worked out for this many fractional positions between two input samples
+const PHASES: usize = 256;
+// keeps the cutoff a little under the output's nyquist so the filter's slope fits below it
+const CUTOFF: f64 = 0.9;
+
+// converts samples from the emulated rate to the device's with a windowed sinc, cutting
+// everything the device rate can't hold so it doesn't alias back down as noise. the ratio
+// can be nudged a little while running for rate control
+pub struct Resampler {
+    // input samples per output sample, before adjustment
+    step: f64,
+    adjustment: f64,
+    // taps either side, for each phase
+    taps: usize,
+    kernels: Vec<Vec<f32>>,
+    history: Vec<Sample>,
+    // of the next output sample, in input samples from the start of history
+    position: f64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let step = input_rate as f64 / output_rate as f64;
+        // in cycles per input sample, 0.5 being the input's nyquist
+        let cutoff = 0.5 * CUTOFF * (1.0 / step).min(1.0);
+        let taps = (ZERO_CROSSINGS / (2.0 * cutoff)).ceil() as usize;
+        let kernels = (0..PHASES)
+            .map(|phase| kernel(cutoff, taps, phase as f64 / PHASES as f64))
+            .collect();
+
+        Self {
+            step,
+            adjustment: 0.0,
+            taps,
+            kernels,
+            // starts on silence so there's history for the first outputs
+            history: vec![Sample::default(); taps - 1],
+            position: (taps - 1) as f64,
+        }
+    }
+
+    // above 0 makes fewer output samples from the same input, below 0 more
+    pub fn set_adjustment(&mut self, adjustment: f64) {
+        self.adjustment = adjustment;
+    }
+
+    pub fn process(&mut self, input: &[Sample]) -> Vec<Sample> {
+        self.history.extend_from_slice(input);
+        let step = self.step * (1.0 + self.adjustment);
+        let mut output = vec![];
+        loop {
+            let mut index = self.position as usize;
+            let mut phase = ((self.position - index as f64) * PHASES as f64).round() as usize;
+            if phase == PHASES {
+                index += 1;
+                phase = 0;
+            }
+            if index + self.taps >= self.history.len() {
+                break;
+            }
+
+            let first = index + 1 - self.taps;
+            let window = &self.history[first..first + self.taps * 2];
+            let mut sample = Sample::default();
+            for (input, weight) in window.iter().zip(&self.kernels[phase]) {
+                sample.left += input.left * weight;
+                sample.right += input.right * weight;
+            }
+            output.push(sample);
+            self.position += step;
+        }
+
+        // drop what no later output can reach
+        let used = (self.position as usize + 1).saturating_sub(self.taps);
+        self.history.drain(..used);
+        self.position -= used as f64;
+        output
+    }
+}
+
+// the taps for an output `fraction` of the way from one input sample to the next, at input
+// offsets 1 - taps to taps. blackman windowed and scaled so a steady level comes out the same
+fn kernel(cutoff: f64, taps: usize, fraction: f64) -> Vec<f32> {
+    let width = taps as f64;
+    let weights: Vec<f64> = (0..taps * 2)
+        .map(|tap| {
+            let distance = tap as f64 + 1.0 - width - fraction;
+            let window = 0.42
+                + 0.5 * (PI * distance / width).cos()
+                + 0.08 * (2.0 * PI * distance / width).cos();
+            sinc(2.0 * cutoff * distance) * window
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights
+        .iter()
+        .map(|weight| (weight / total) as f32)
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(rate: f64, frequency: f64, count: usize) -> Vec<Sample> {
+        (0..count)
+            .map(|n| {
+                let value = (2.0 * PI * frequency * n as f64 / rate).sin() as f32;
+                Sample {
+                    left: value,
+                    right: value,
+                }
+            })
+            .collect()
+    }
+
+    fn peak(samples: &[Sample]) -> f32 {
+        samples
+            .iter()
+            .map(|sample| sample.left.abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_output_count() {
+        let mut resampler = Resampler::new(131072, 48000);
+        let count: usize = (0..100)
+            .map(|_| resampler.process(&vec![Sample::default(); 1310]).len())
+            .sum();
+        assert!((47900..=48000).contains(&count), "{}", count);
+
+        resampler.set_adjustment(0.01);
+        let count = resampler.process(&vec![Sample::default(); 131072]).len();
+        assert!((47500..47550).contains(&count), "{}", count);
+    }
+
+    #[test]
+    fn test_passes_audible_tones() {
+        let mut resampler = Resampler::new(131072, 48000);
+        let output = resampler.process(&tone(131072.0, 1000.0, 13107));
+
+        assert!((peak(&output[1000..]) - 1.0).abs() < 0.01);
+    }
+
+    // a tone above the device's nyquist would alias to 8 kHz without the filter
+    #[test]
+    fn test_cuts_tones_above_nyquist() {
+        let mut resampler = Resampler::new(131072, 48000);
+        let output = resampler.process(&tone(131072.0, 40000.0, 13107));
+
+        assert!(peak(&output[1000..]) < 0.01);
+    }
+}
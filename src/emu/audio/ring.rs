@@ -0,0 +1,150 @@
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::gameboy::Sample;
+
+// a queue of samples between the emulator and the audio callback that never locks, so the
+// callback can't be held up by a frame being emulated. one thread pushes and one pops, each
+// sample lives in an atomic slot as its two f32s' bits
+struct Shared {
+    slots: Box<[AtomicU64]>,
+    // how many samples have ever been pushed and popped, the slot is the count modulo capacity
+    pushed: AtomicUsize,
+    popped: AtomicUsize,
+}
+
+impl Shared {
+    fn len(&self) -> usize {
+        let popped = self.popped.load(Ordering::Acquire);
+        self.pushed.load(Ordering::Acquire).wrapping_sub(popped)
+    }
+
+    fn slot(&self, count: usize) -> &AtomicU64 {
+        &self.slots[count % self.slots.len()]
+    }
+}
+
+pub struct Producer {
+    shared: Arc<Shared>,
+}
+
+pub struct Consumer {
+    shared: Arc<Shared>,
+}
+
+pub fn ring_buffer(capacity: usize) -> (Producer, Consumer) {
+    let shared = Arc::new(Shared {
+        slots: (0..capacity).map(|_| AtomicU64::new(0)).collect(),
+        pushed: AtomicUsize::new(0),
+        popped: AtomicUsize::new(0),
+    });
+    let producer = Producer {
+        shared: shared.clone(),
+    };
+    (producer, Consumer { shared })
+}
+
+impl Producer {
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    // pushes as many as fit and returns how many that was
+    pub fn push(&self, samples: &[Sample]) -> usize {
+        let count = samples.len().min(self.capacity() - self.len());
+        let pushed = self.shared.pushed.load(Ordering::Relaxed);
+        for (offset, sample) in samples[..count].iter().enumerate() {
+            let slot = self.shared.slot(pushed.wrapping_add(offset));
+            slot.store(pack(*sample), Ordering::Relaxed);
+        }
+        // publishes the slots written above
+        self.shared
+            .pushed
+            .store(pushed.wrapping_add(count), Ordering::Release);
+        count
+    }
+}
+
+impl Consumer {
+    pub fn pop(&self) -> Option<Sample> {
+        let popped = self.shared.popped.load(Ordering::Relaxed);
+        if self.shared.pushed.load(Ordering::Acquire) == popped {
+            return None;
+        }
+        let sample = unpack(self.shared.slot(popped).load(Ordering::Relaxed));
+        // hands the slot back to the producer
+        self.shared
+            .popped
+            .store(popped.wrapping_add(1), Ordering::Release);
+        Some(sample)
+    }
+}
+
+fn pack(sample: Sample) -> u64 {
+    (sample.left.to_bits() as u64) << 32 | sample.right.to_bits() as u64
+}
+
+fn unpack(bits: u64) -> Sample {
+    Sample {
+        left: f32::from_bits((bits >> 32) as u32),
+        right: f32::from_bits(bits as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(value: f32) -> Sample {
+        Sample {
+            left: value,
+            right: -value,
+        }
+    }
+
+    #[test]
+    fn test_push_until_full() {
+        let (producer, consumer) = ring_buffer(3);
+        let samples: Vec<Sample> = (0..4).map(|n| sample(n as f32)).collect();
+
+        assert_eq!(producer.push(&samples), 3);
+        assert_eq!(producer.len(), 3);
+        assert_eq!(consumer.pop(), Some(sample(0.0)));
+        assert_eq!(producer.push(&samples[3..]), 1);
+        assert_eq!(
+            [(); 4].map(|_| consumer.pop()),
+            [
+                Some(sample(1.0)),
+                Some(sample(2.0)),
+                Some(sample(3.0)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let (producer, consumer) = ring_buffer(16);
+        let reader = std::thread::spawn(move || {
+            let mut values = vec![];
+            while values.len() < 1000 {
+                values.extend(consumer.pop().map(|sample| sample.left));
+            }
+            values
+        });
+
+        let samples: Vec<Sample> = (0..1000).map(|n| sample(n as f32)).collect();
+        let mut written = 0;
+        while written < samples.len() {
+            written += producer.push(&samples[written..]);
+        }
+        let expected: Vec<f32> = (0..1000).map(|n| n as f32).collect();
+        assert_eq!(reader.join().unwrap(), expected);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+};
+
+use super::{resampler::Resampler, EMULATED_RATE};
+use crate::gameboy::{Sample, Stems};
+
+// recordings are resampled to a rate anything can play
+pub const WAV_RATE: u32 = 48000;
+const STEM_NAMES: [&str; 4] = ["pulse1", "pulse2", "wave", "noise"];
+const CHANNELS: u16 = 2;
+const BYTES_PER_SAMPLE: u16 = 2;
+const HEADER_SIZE: u32 = 44;
+const BLOCK_ALIGN: u32 = (CHANNELS * BYTES_PER_SAMPLE) as u32;
+// the riff size after the first 8 bytes has to fit in 32 bits, a little over 6 hours at 48 kHz
+const MAX_SAMPLES: u32 = (u32::MAX - (HEADER_SIZE - 8)) / BLOCK_ALIGN;
+
+// a 16 bit stereo pcm wav file. its sizes aren't known until the end, so finish rewrites
+// the header with them. once it's full, samples past the size limit are dropped
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    path: String,
+    sample_rate: u32,
+    samples: u32,
+    max_samples: u32,
+}
+
+impl WavWriter {
+    pub fn new(path: &str, sample_rate: u32) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let mut wav = Self {
+            writer: BufWriter::new(file),
+            path: path.to_string(),
+            sample_rate,
+            samples: 0,
+            max_samples: MAX_SAMPLES,
+        };
+        wav.writer.write_all(&header(sample_rate, 0))?;
+        Ok(wav)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.samples == self.max_samples
+    }
+
+    pub fn write_samples(&mut self, samples: &[Sample]) -> io::Result<()> {
+        let room = (self.max_samples - self.samples) as usize;
+        let samples = &samples[..samples.len().min(room)];
+        let mut data = Vec::with_capacity(samples.len() * BLOCK_ALIGN as usize);
+        for sample in samples {
+            data.extend(to_pcm(sample.left).to_le_bytes());
+            data.extend(to_pcm(sample.right).to_le_bytes());
+        }
+        self.writer.write_all(&data)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let header = header(self.sample_rate, self.samples * BLOCK_ALIGN);
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.flush()
+    }
+}
+
+// records the emulated sound as a wav, and each channel into its own file alongside it if
+// asked: song.wav with song.pulse1.wav, song.pulse2.wav, song.wave.wav and song.noise.wav.
+// the samples are resampled from EMULATED_RATE without any rate control, so a recording
+// comes out the same whether it was made in the window or headless
+pub struct WavRecorder {
+    mix: Track,
+    stems: Option<Vec<Track>>,
+}
+
+struct Track {
+    resampler: Resampler,
+    wav: WavWriter,
+}
+
+impl Track {
+    fn new(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            resampler: Resampler::new(EMULATED_RATE, WAV_RATE),
+            wav: WavWriter::new(path, WAV_RATE)?,
+        })
+    }
+
+    fn write(&mut self, samples: &[Sample]) -> io::Result<()> {
+        let resampled = self.resampler.process(samples);
+        self.wav.write_samples(&resampled)
+    }
+}
+
+impl WavRecorder {
+    pub fn new(path: &str, stems: bool) -> io::Result<Self> {
+        let mix = Track::new(path)?;
+        let stems = if stems {
+            let tracks = STEM_NAMES
+                .iter()
+                .map(|name| Track::new(&stem_path(path, name)))
+                .collect::<io::Result<_>>()?;
+            Some(tracks)
+        } else {
+            None
+        };
+        Ok(Self { mix, stems })
+    }
+
+    pub fn has_stems(&self) -> bool {
+        self.stems.is_some()
+    }
+
+    pub fn path(&self) -> &str {
+        self.mix.wav.path()
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.mix.wav.samples() as f32 / WAV_RATE as f32
+    }
+
+    // the stems fill up along with the mix
+    pub fn is_full(&self) -> bool {
+        self.mix.wav.is_full()
+    }
+
+    // stems are ignored unless they're being recorded
+    pub fn write(&mut self, samples: &[Sample], stems: &[Stems]) -> io::Result<()> {
+        self.mix.write(samples)?;
+        for (channel, track) in self.stems.iter_mut().flatten().enumerate() {
+            let samples: Vec<Sample> = stems.iter().map(|stems| stems[channel]).collect();
+            track.write(&samples)?;
+        }
+        Ok(())
+    }
+
+    // every file gets its header, even after one of them fails
+    pub fn finish(self) -> io::Result<()> {
+        let mut result = self.mix.wav.finish();
+        for track in self.stems.into_iter().flatten() {
+            result = result.and(track.wav.finish());
+        }
+        result
+    }
+}
+
+fn stem_path(path: &str, name: &str) -> String {
+    let base = path.strip_suffix(".wav").unwrap_or(path);
+    format!("{}.{}.wav", base, name)
+}
+
+fn header(sample_rate: u32, data_size: u32) -> Vec<u8> {
+    let block_align = CHANNELS * BYTES_PER_SAMPLE;
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend(b"RIFF");
+    header.extend((HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend(b"WAVEfmt ");
+    header.extend(16u32.to_le_bytes());
+    // pcm
+    header.extend(1u16.to_le_bytes());
+    header.extend(CHANNELS.to_le_bytes());
+    header.extend(sample_rate.to_le_bytes());
+    header.extend((sample_rate * block_align as u32).to_le_bytes());
+    header.extend(block_align.to_le_bytes());
+    header.extend((BYTES_PER_SAMPLE * 8).to_le_bytes());
+    header.extend(b"data");
+    header.extend(data_size.to_le_bytes());
+    header
+}
+
+fn to_pcm(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_write_samples() {
+        let path = temp_path("garlickboy_test_write_samples.wav");
+        let mut wav = WavWriter::new(&path, 48000).unwrap();
+        let samples = [
+            Sample {
+                left: 1.0,
+                right: -1.0,
+            },
+            Sample {
+                left: 0.5,
+                right: 2.0,
+            },
+        ];
+        wav.write_samples(&samples).unwrap();
+        assert_eq!(wav.samples(), 2);
+        wav.finish().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(data[4..8], 44u32.to_le_bytes());
+        assert_eq!(data[24..28], 48000u32.to_le_bytes());
+        assert_eq!(data[40..44], 8u32.to_le_bytes());
+        assert_eq!(data[44..], [0xFF, 0x7F, 0x01, 0x80, 0x00, 0x40, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn test_stops_at_the_size_limit() {
+        let path = temp_path("garlickboy_test_stops_at_the_size_limit.wav");
+        let mut wav = WavWriter::new(&path, 48000).unwrap();
+        wav.max_samples = 3;
+        wav.write_samples(&[Sample::default(); 2]).unwrap();
+        assert!(!wav.is_full());
+        wav.write_samples(&[Sample::default(); 2]).unwrap();
+        assert!(wav.is_full());
+        wav.write_samples(&[Sample::default(); 2]).unwrap();
+        wav.finish().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.len(), 44 + 3 * 4);
+        assert_eq!(data[40..44], 12u32.to_le_bytes());
+        assert_eq!(MAX_SAMPLES as u64 * 4 + 36, u32::MAX as u64 - 3);
+    }
+
+    #[test]
+    fn test_unwritable_path_fails() {
+        let path = temp_path("garlickboy_test_missing_directory/song.wav");
+        assert!(WavRecorder::new(&path, false).is_err());
+    }
+
+    #[test]
+    fn test_stems() {
+        let path = temp_path("garlickboy_test_stems.wav");
+        let mut recorder = WavRecorder::new(&path, true).unwrap();
+        let tone = Sample {
+            left: 0.5,
+            right: 0.5,
+        };
+        let stems = [
+            tone,
+            Sample::default(),
+            Sample::default(),
+            Sample::default(),
+        ];
+        recorder.write(&[tone; 1000], &[stems; 1000]).unwrap();
+        recorder.finish().unwrap();
+
+        let read = |path: &str| {
+            let data = std::fs::read(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            data
+        };
+        let mix = read(&path);
+        let pulse1 = read(&stem_path(&path, "pulse1"));
+        let noise = read(&stem_path(&path, "noise"));
+        read(&stem_path(&path, "pulse2"));
+        read(&stem_path(&path, "wave"));
+        assert_eq!(stem_path("song.wav", "wave"), "song.wave.wav");
+        assert!(mix.len() > HEADER_SIZE as usize);
+        assert_eq!(mix, pulse1);
+        assert!(noise[HEADER_SIZE as usize..].iter().all(|byte| *byte == 0));
+    }
+}
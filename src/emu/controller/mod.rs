@@ -1,25 +1,13 @@
+#[cfg(feature = "sdl")]
 mod keyboard;
-use keyboard::Keyboard;
-use sdl2::EventPump;
+mod terminal;
 
-const DOWN_BIT: u8 = 1 << 3;
-const UP_BIT: u8 = 1 << 2;
-const LEFT_BIT: u8 = 1 << 1;
-const RIGHT_BIT: u8 = 1;
-const START_BIT: u8 = 1 << 3;
-const SELECT_BIT: u8 = 1 << 2;
-const B_BIT: u8 = 1 << 1;
-const A_BIT: u8 = 1;
+#[cfg(feature = "sdl")]
+pub(crate) use self::keyboard::Keyboard;
+pub(crate) use self::terminal::TerminalController;
+use crate::gameboy::Buttons;
 
-#[cfg(test)]
-use mockall::automock;
-#[cfg_attr(test, automock)]
+// a source of input for the gameboy, read once per frame
 pub trait Controller {
-    fn actions(&self) -> u8;
-    fn directions(&self) -> u8;
-    fn read(&mut self, event_pump: &EventPump);
-}
-
-pub fn new_keyboard_controller() -> Box<dyn Controller> {
-    Keyboard::new()
+    fn buttons(&mut self) -> Buttons;
 }
@@ -0,0 +1,248 @@
+use std::{
+    io::{self, Read},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use super::*;
+
+const ESCAPE: u8 = 0x1B;
+const CTRL_C: u8 = 0x03;
+// terminals only report key presses, with repeats while a key is held, so each press holds
+// its button down for a while. long enough to bridge the usual delay before keys repeat
+const HOLD_FRAMES: u8 = 15;
+// how long an escape has to be on its own before it counts as the escape key, rather than
+// the start of an arrow's escape sequence that a slow connection split in two
+const ESCAPE_FRAMES: u8 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Key {
+    Start,
+    Select,
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    Quit,
+}
+
+// reads the keyboard from stdin with the terminal in raw mode, using the same keys as the
+// window: arrows, x, z, enter and backspace. q, ctrl-c or escape on its own quits
+pub(crate) struct TerminalController {
+    keys: Receiver<Vec<u8>>,
+    buffer: KeyBuffer,
+    // frames left that each of start, select, up, down, left, right, a and b stays held
+    held: [u8; 8],
+    quit: bool,
+    terminal_settings: String,
+}
+
+impl TerminalController {
+    pub fn new() -> Self {
+        let terminal_settings = stty(&["-g"]);
+        stty(&["raw", "-echo"]);
+
+        // stdin blocks, so it's read on its own thread and drained once a frame
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0; 64];
+            while let Ok(count @ 1..) = io::stdin().read(&mut buffer) {
+                if sender.send(buffer[..count].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            keys,
+            buffer: KeyBuffer::new(),
+            held: [0; 8],
+            quit: false,
+            terminal_settings: terminal_settings.trim().to_string(),
+        }
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+}
+
+impl Controller for TerminalController {
+    fn buttons(&mut self) -> Buttons {
+        let bytes: Vec<u8> = self.keys.try_iter().flatten().collect();
+        for key in self.buffer.keys(&bytes) {
+            match key {
+                Key::Quit => self.quit = true,
+                button => self.held[button as usize] = HOLD_FRAMES,
+            }
+        }
+
+        let [start, select, up, down, left, right, a, b] = self.held.map(|frames| frames > 0);
+        self.held = self.held.map(|frames| frames.saturating_sub(1));
+        Buttons {
+            start,
+            select,
+            up,
+            down,
+            left,
+            right,
+            a,
+            b,
+        }
+    }
+}
+
+impl Drop for TerminalController {
+    fn drop(&mut self) {
+        stty(&[self.terminal_settings.as_str()]);
+    }
+}
+
+// stty changes whichever terminal its stdin is, so it's given ours
+fn stty(args: &[&str]) -> String {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .expect("failed to run stty");
+    if !output.status.success() {
+        panic!("stdin isn't a terminal, stty {} failed", args.join(" "));
+    }
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+// what's been read that doesn't make a whole key yet, kept for the next frame
+struct KeyBuffer {
+    pending: Vec<u8>,
+    // frames a lone escape has been waiting
+    escape_frames: u8,
+}
+
+impl KeyBuffer {
+    fn new() -> Self {
+        Self {
+            pending: vec![],
+            escape_frames: 0,
+        }
+    }
+
+    // called once a frame with whatever was read since the last
+    fn keys(&mut self, bytes: &[u8]) -> Vec<Key> {
+        self.pending.extend_from_slice(bytes);
+        let (mut keys, used) = parse_keys(&self.pending);
+        self.pending.drain(..used);
+
+        if self.pending != [ESCAPE] {
+            self.escape_frames = 0;
+            return keys;
+        }
+        self.escape_frames += 1;
+        if self.escape_frames == ESCAPE_FRAMES {
+            self.pending.clear();
+            self.escape_frames = 0;
+            keys.push(Key::Quit);
+        }
+        keys
+    }
+}
+
+// the keys in `bytes` and how many bytes they took. an escape sequence cut off at the end
+// is left unparsed until the rest arrives
+fn parse_keys(bytes: &[u8]) -> (Vec<Key>, usize) {
+    let mut keys = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let (key, length) = match bytes[index..] {
+            [ESCAPE] | [ESCAPE, b'[' | b'O'] => break,
+            // arrows are escape sequences, ESC [ A in normal mode or ESC O A in application
+            // mode. ESC [ can have parameters before its final byte, eg ESC [ 1 ; 5 A for
+            // ctrl and up
+            [ESCAPE, b'O', code, ..] => (arrow(code), 3),
+            [ESCAPE, b'[', ..] => {
+                let sequence = &bytes[index + 2..];
+                let Some(end) = sequence
+                    .iter()
+                    .position(|byte| (0x40..=0x7E).contains(byte))
+                else {
+                    break;
+                };
+                (arrow(sequence[end]), end + 3)
+            }
+            // alt and a key
+            [ESCAPE, _, ..] => (None, 2),
+            [CTRL_C | b'q' | b'Q', ..] => (Some(Key::Quit), 1),
+            [b'\r' | b'\n', ..] => (Some(Key::Start), 1),
+            [0x7F | 0x08, ..] => (Some(Key::Select), 1),
+            [b'x' | b'X', ..] => (Some(Key::A), 1),
+            [b'z' | b'Z', ..] => (Some(Key::B), 1),
+            _ => (None, 1),
+        };
+        keys.extend(key);
+        index += length;
+    }
+    (keys, index)
+}
+
+fn arrow(code: u8) -> Option<Key> {
+    match code {
+        b'A' => Some(Key::Up),
+        b'B' => Some(Key::Down),
+        b'C' => Some(Key::Right),
+        b'D' => Some(Key::Left),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"x\x1b[Az\x1bOD\r\x7f?\x1b[1;5B"),
+            (
+                vec![
+                    Key::A,
+                    Key::Up,
+                    Key::B,
+                    Key::Left,
+                    Key::Start,
+                    Key::Select,
+                    Key::Down
+                ],
+                17
+            )
+        );
+        assert_eq!(parse_keys(b"\x03q"), (vec![Key::Quit, Key::Quit], 2));
+        // alt and x isn't a quit or an a
+        assert_eq!(parse_keys(b"\x1bx"), (vec![], 2));
+    }
+
+    #[test]
+    fn test_parse_keys_leaves_cut_off_sequences() {
+        assert_eq!(parse_keys(b"x\x1b"), (vec![Key::A], 1));
+        assert_eq!(parse_keys(b"x\x1b["), (vec![Key::A], 1));
+        assert_eq!(parse_keys(b"\x1b[1;5"), (vec![], 0));
+    }
+
+    #[test]
+    fn test_key_buffer_joins_split_sequences() {
+        let mut buffer = KeyBuffer::new();
+        assert_eq!(buffer.keys(b"z\x1b"), vec![Key::B]);
+        assert_eq!(buffer.keys(b""), vec![]);
+        assert_eq!(buffer.keys(b"[C"), vec![Key::Right]);
+    }
+
+    #[test]
+    fn test_key_buffer_lone_escape_quits() {
+        let mut buffer = KeyBuffer::new();
+        assert_eq!(buffer.keys(b"\x1b"), vec![]);
+        let keys: Vec<Key> = (1..ESCAPE_FRAMES).flat_map(|_| buffer.keys(b"")).collect();
+        assert_eq!(keys, vec![Key::Quit]);
+        assert_eq!(buffer.keys(b""), vec![]);
+    }
+}
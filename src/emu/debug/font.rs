@@ -0,0 +1,31 @@
+// tiny 3x5 hex font for labelling the debug views without needing sdl2_ttf
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+pub const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+const GLYPHS: [[u8; GLYPH_HEIGHT]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b010, 0b010, 0b010], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b111, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b111, 0b100, 0b100, 0b100, 0b111], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b111, 0b100, 0b111], // E
+    [0b111, 0b100, 0b111, 0b100, 0b100], // F
+];
+
+pub fn glyph(digit: u8) -> [u8; GLYPH_HEIGHT] {
+    GLYPHS[(digit & 0xF) as usize]
+}
+
+pub fn is_set(glyph: &[u8; GLYPH_HEIGHT], x: usize, y: usize) -> bool {
+    glyph[y] >> (GLYPH_WIDTH - 1 - x) & 1 == 1
+}
@@ -0,0 +1,164 @@
+mod font;
+mod views;
+
+use sdl2::{
+    pixels::PixelFormatEnum,
+    render::{Canvas, Texture, TextureCreator},
+    video::{Window, WindowContext},
+    Sdl, VideoSubsystem,
+};
+
+use self::views::Surface;
+use crate::gameboy::PpuState;
+
+const DEBUG_SCALE: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    Tiles,
+    Maps,
+    Oam,
+    Palettes,
+}
+
+impl DebugView {
+    fn title(&self) -> &'static str {
+        match self {
+            DebugView::Tiles => "GarlickBoy - Tiles",
+            DebugView::Maps => "GarlickBoy - BG Maps",
+            DebugView::Oam => "GarlickBoy - OAM",
+            DebugView::Palettes => "GarlickBoy - Palettes",
+        }
+    }
+
+    fn render(&self, state: &PpuState) -> Surface {
+        match self {
+            DebugView::Tiles => views::tiles(state),
+            DebugView::Maps => views::maps(state),
+            DebugView::Oam => views::oam(state),
+            DebugView::Palettes => views::palettes(state),
+        }
+    }
+}
+
+struct DebugWindow {
+    view: DebugView,
+    // kept between frames and only remade when the view changes size. it's declared first
+    // so it's dropped before the renderer it belongs to
+    texture: Texture<'static>,
+    texture_creator: TextureCreator<WindowContext>,
+    canvas: Canvas<Window>,
+}
+
+impl DebugWindow {
+    fn new(video: &VideoSubsystem, view: DebugView, state: &PpuState) -> Self {
+        let surface = view.render(state);
+        let window = video
+            .window(
+                view.title(),
+                surface.width as u32 * DEBUG_SCALE,
+                surface.height as u32 * DEBUG_SCALE,
+            )
+            .build()
+            .expect("failed to build debug window");
+        let canvas = window
+            .into_canvas()
+            .build()
+            .expect("failed to convert debug window into canvas");
+
+        let texture_creator = canvas.texture_creator();
+        let texture = create_texture(&texture_creator, &surface);
+
+        let mut debug_window = Self {
+            view,
+            texture,
+            texture_creator,
+            canvas,
+        };
+        debug_window.present(&surface);
+        debug_window
+    }
+
+    fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    fn update(&mut self, state: &PpuState) {
+        let surface = self.view.render(state);
+        self.present(&surface);
+    }
+
+    fn present(&mut self, surface: &Surface) {
+        let query = self.texture.query();
+        if (query.width as usize, query.height as usize) != (surface.width, surface.height) {
+            self.texture = create_texture(&self.texture_creator, surface);
+        }
+        let data: Vec<u8> = surface
+            .pixels
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b])
+            .collect();
+        self.texture
+            .update(None, &data, surface.width * 3)
+            .expect("failed to update debug texture");
+        self.canvas.copy(&self.texture, None, None).unwrap();
+        self.canvas.present();
+    }
+}
+
+fn create_texture(
+    texture_creator: &TextureCreator<WindowContext>,
+    surface: &Surface,
+) -> Texture<'static> {
+    let texture = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            surface.width as u32,
+            surface.height as u32,
+        )
+        .expect("failed to create debug texture");
+    // the texture creator lives alongside it in the window
+    unsafe { std::mem::transmute::<_, Texture<'static>>(texture) }
+}
+
+// optional windows showing vram, oam and palettes, refreshed once per frame
+pub struct DebugWindows {
+    video: VideoSubsystem,
+    windows: Vec<DebugWindow>,
+}
+
+impl DebugWindows {
+    pub fn new(sdl: &Sdl) -> Self {
+        let video = sdl.video().expect("failed to get video subsystem");
+        Self {
+            video,
+            windows: vec![],
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    pub fn toggle(&mut self, view: DebugView, state: &PpuState) {
+        if let Some(index) = self.windows.iter().position(|window| window.view == view) {
+            self.windows.remove(index);
+        } else {
+            let window = DebugWindow::new(&self.video, view, state);
+            self.windows.push(window);
+        }
+    }
+
+    // returns false when the window isn't one of ours
+    pub fn close(&mut self, window_id: u32) -> bool {
+        let count = self.windows.len();
+        self.windows.retain(|window| window.id() != window_id);
+        self.windows.len() != count
+    }
+
+    pub fn update(&mut self, state: &PpuState) {
+        for window in self.windows.iter_mut() {
+            window.update(state);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use super::font::{glyph, is_set, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::gameboy::{Color, PpuState, Sprite, TILE_DATA_TILES};
+
+type Tile = [[u8; 8]; 8];
+
+const BACKGROUND: Color = Color::rgb(0xE0, 0xE0, 0xE0);
+const TEXT: Color = Color::rgb(0x20, 0x20, 0x20);
+const VIEWPORT: Color = Color::rgb(0xFF, 0x00, 0x00);
+const TRANSPARENT: Color = Color::rgb(0xFF, 0x00, 0xFF);
+
+const TILES_PER_ROW: usize = 16;
+const MAP_SIZE: usize = 256;
+const MAP_GAP: usize = 8;
+const OAM_COLUMNS: usize = 2;
+const OAM_ROWS: usize = 20;
+const OAM_ROW_HEIGHT: usize = 18;
+const OAM_COLUMN_WIDTH: usize = 100;
+const SWATCH_SIZE: usize = 16;
+
+pub struct Surface {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Surface {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for dy in 0..height {
+            for dx in 0..width {
+                self.set_pixel(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    fn outline_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for dx in 0..width {
+            self.set_pixel(x + dx, y, color);
+            self.set_pixel(x + dx, y + height - 1, color);
+        }
+        for dy in 0..height {
+            self.set_pixel(x, y + dy, color);
+            self.set_pixel(x + width - 1, y + dy, color);
+        }
+    }
+
+    fn draw_hex(&mut self, x: usize, y: usize, value: u8) {
+        for (i, digit) in [value >> 4, value & 0xF].iter().enumerate() {
+            let glyph = glyph(*digit);
+            for gy in 0..GLYPH_HEIGHT {
+                for gx in 0..GLYPH_WIDTH {
+                    if is_set(&glyph, gx, gy) {
+                        self.set_pixel(x + i * GLYPH_ADVANCE + gx, y + gy, TEXT);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_tile(&mut self, tile: &Tile, x: usize, y: usize, colors: &[Color]) {
+        for (row, indexes) in tile.iter().enumerate() {
+            for (col, index) in indexes.iter().enumerate() {
+                self.set_pixel(x + col, y + row, colors[*index as usize]);
+            }
+        }
+    }
+}
+
+fn decode_tile(state: &PpuState, bank: usize, tile: usize) -> Tile {
+    let mut rows = [[0; 8]; 8];
+    for (row, indexes) in rows.iter_mut().enumerate() {
+        *indexes = state.tile_row(bank, tile, row as u8);
+    }
+    rows
+}
+
+// all 384 tiles in the tile data area, 0x8000 at the top left. on cgb bank 1 is drawn
+// to the right of bank 0
+pub fn tiles(state: &PpuState) -> Surface {
+    let banks = if state.cgb { 2 } else { 1 };
+    let bank_width = TILES_PER_ROW * 8;
+    let rows = TILE_DATA_TILES / TILES_PER_ROW;
+    let mut surface = Surface::new(banks * bank_width + (banks - 1) * MAP_GAP, rows * 8);
+    for bank in 0..banks {
+        let offset = bank * (bank_width + MAP_GAP);
+        for tile in 0..TILE_DATA_TILES {
+            let x = offset + (tile % TILES_PER_ROW) * 8;
+            let y = (tile / TILES_PER_ROW) * 8;
+            let rows = decode_tile(state, bank, tile);
+            surface.draw_tile(&rows, x, y, &state.background_colors);
+        }
+    }
+    surface
+}
+
+// both 32x32 bg maps side by side, with the scx/scy viewport drawn over the active one.
+// on cgb each entry's tile comes from the bank and is flipped as its attributes say
+pub fn maps(state: &PpuState) -> Surface {
+    let mut surface = Surface::new(MAP_SIZE * 2 + MAP_GAP, MAP_SIZE);
+    for map in 0..2 {
+        let offset = map * (MAP_SIZE + MAP_GAP);
+        for y in 0..32 {
+            for x in 0..32 {
+                let attributes = state.map_attributes(map, x, y);
+                let mut rows = decode_tile(state, attributes.bank(), state.map_tile(map, x, y));
+                if attributes.is_y_flipped() {
+                    rows.reverse();
+                }
+                if attributes.is_x_flipped() {
+                    rows.iter_mut().for_each(|row| row.reverse());
+                }
+                surface.draw_tile(&rows, offset + x * 8, y * 8, &state.background_colors);
+            }
+        }
+    }
+
+    let offset = state.active_bg_map() * (MAP_SIZE + MAP_GAP);
+    let left = state.scrollx as usize;
+    let top = state.scrolly as usize;
+    for dx in 0..160 {
+        let x = offset + (left + dx) % MAP_SIZE;
+        surface.set_pixel(x, top, VIEWPORT);
+        surface.set_pixel(x, (top + 143) % MAP_SIZE, VIEWPORT);
+    }
+    for dy in 0..144 {
+        let y = (top + dy) % MAP_SIZE;
+        surface.set_pixel(offset + left, y, VIEWPORT);
+        surface.set_pixel(offset + (left + 159) % MAP_SIZE, y, VIEWPORT);
+    }
+    surface
+}
+
+// one row per sprite: preview, index, y, x, tile, attributes and
+// boxes for the bg priority, y flip, x flip and palette flags
+pub fn oam(state: &PpuState) -> Surface {
+    let mut surface = Surface::new(OAM_COLUMNS * OAM_COLUMN_WIDTH, OAM_ROWS * OAM_ROW_HEIGHT);
+    let height = state.obj_height();
+
+    for sprite in state.sprites() {
+        let x = (sprite.index / OAM_ROWS) * OAM_COLUMN_WIDTH + 1;
+        let y = (sprite.index % OAM_ROWS) * OAM_ROW_HEIGHT + 1;
+        draw_sprite(&mut surface, state, &sprite, height, x, y);
+
+        let text_y = y + 5;
+        let values = [
+            sprite.index as u8,
+            sprite.y,
+            sprite.x,
+            sprite.tile,
+            sprite.attributes,
+        ];
+        for (i, value) in values.iter().enumerate() {
+            surface.draw_hex(x + 10 + i * GLYPH_ADVANCE * 3, text_y, *value);
+        }
+
+        let flags = [
+            sprite.is_behind_bg(),
+            sprite.is_y_flipped(),
+            sprite.is_x_flipped(),
+            sprite.is_palette1(),
+        ];
+        for (i, flag) in flags.iter().enumerate() {
+            let flag_x = x + 72 + i * 6;
+            if *flag {
+                surface.fill_rect(flag_x, text_y, 5, 5, TEXT);
+            } else {
+                surface.outline_rect(flag_x, text_y, 5, 5, TEXT);
+            }
+        }
+    }
+    surface
+}
+
+fn draw_sprite(
+    surface: &mut Surface,
+    state: &PpuState,
+    sprite: &Sprite,
+    height: u8,
+    x: usize,
+    y: usize,
+) {
+    let mut colors = if sprite.is_palette1() {
+        state.object1_colors
+    } else {
+        state.object0_colors
+    };
+    colors[0] = TRANSPARENT;
+    let bank = if state.cgb { sprite.bank() } else { 0 };
+    let tile = sprite.tile(height) as usize;
+    surface.draw_tile(&decode_tile(state, bank, tile), x, y, &colors);
+    if height == 16 {
+        surface.draw_tile(&decode_tile(state, bank, tile + 1), x, y + 8, &colors);
+    }
+}
+
+// the register value and the 4 colors it currently maps to for BGP, OBP0 and OBP1
+pub fn palettes(state: &PpuState) -> Surface {
+    let rows = [
+        (state.bgp, state.background_colors),
+        (state.obp0, state.object0_colors),
+        (state.obp1, state.object1_colors),
+    ];
+    let row_height = SWATCH_SIZE + 4;
+    let mut surface = Surface::new(12 + 4 * (SWATCH_SIZE + 2), rows.len() * row_height);
+
+    for (row, (byte, colors)) in rows.iter().enumerate() {
+        let y = row * row_height + 2;
+        surface.draw_hex(2, y + (SWATCH_SIZE - GLYPH_HEIGHT) / 2, *byte);
+        for (i, color) in colors.iter().enumerate() {
+            let x = 12 + i * (SWATCH_SIZE + 2);
+            surface.fill_rect(x, y, SWATCH_SIZE, SWATCH_SIZE, *color);
+            surface.outline_rect(x, y, SWATCH_SIZE, SWATCH_SIZE, TEXT);
+        }
+    }
+    surface
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = Color::rgb(0xFF, 0xFF, 0xFF);
+    const BLACK: Color = Color::rgb(0x00, 0x00, 0x00);
+
+    impl Surface {
+        fn pixel(&self, x: usize, y: usize) -> Color {
+            self.pixels[y * self.width + x]
+        }
+    }
+
+    fn state() -> PpuState {
+        PpuState {
+            vram: vec![0; 0x4000],
+            cgb: false,
+            oam: vec![0; 0xA0],
+            lcdc: 0x91,
+            scrollx: 0,
+            scrolly: 0,
+            bgp: 0xE4,
+            obp0: 0xE4,
+            obp1: 0xE4,
+            background_colors: [WHITE, WHITE, WHITE, BLACK],
+            object0_colors: [WHITE, WHITE, WHITE, BLACK],
+            object1_colors: [WHITE, WHITE, WHITE, BLACK],
+        }
+    }
+
+    #[test]
+    fn test_tiles_layout() {
+        let mut state = state();
+        // tile 17, first row fully colour 3
+        state.vram[17 * 16] = 0xFF;
+        state.vram[17 * 16 + 1] = 0xFF;
+
+        let surface = tiles(&state);
+
+        assert_eq!((surface.width, surface.height), (128, 192));
+        assert_eq!(surface.pixel(8, 8), BLACK);
+        assert_eq!(surface.pixel(15, 8), BLACK);
+        assert_eq!(surface.pixel(8, 9), WHITE);
+    }
+
+    #[test]
+    fn test_tiles_shows_bank_1_on_cgb() {
+        let mut state = state();
+        state.cgb = true;
+        // bank 1 tile 1, first row fully colour 3
+        state.vram[0x2000 + 16] = 0xFF;
+        state.vram[0x2000 + 17] = 0xFF;
+
+        let surface = tiles(&state);
+
+        assert_eq!((surface.width, surface.height), (128 * 2 + MAP_GAP, 192));
+        assert_eq!(surface.pixel(8, 0), WHITE);
+        assert_eq!(surface.pixel(128 + MAP_GAP + 8, 0), BLACK);
+    }
+
+    #[test]
+    fn test_maps_use_cgb_attributes() {
+        let mut state = state();
+        state.cgb = true;
+        // map 0 entry (1, 0) is tile 0 from bank 1, y flipped
+        state.vram[0x2000 + 0x1800 + 1] = 0b0100_1000;
+        state.vram[0x2000] = 0xFF;
+        state.vram[0x2001] = 0xFF;
+
+        let surface = maps(&state);
+
+        assert_eq!(surface.pixel(8, 7), BLACK);
+        assert_eq!(surface.pixel(8, 1), WHITE);
+        // the same entry with dmg ignores bank 1
+        state.cgb = false;
+        assert_eq!(maps(&state).pixel(8, 7), WHITE);
+    }
+
+    #[test]
+    fn test_maps_viewport_wraps() {
+        let mut state = state();
+        state.scrollx = 200;
+        state.scrolly = 250;
+
+        let surface = maps(&state);
+
+        assert_eq!(surface.pixel(200, 250), VIEWPORT);
+        assert_eq!(surface.pixel(255, 250), VIEWPORT);
+        assert_eq!(surface.pixel(0, 250), VIEWPORT);
+        assert_eq!(surface.pixel(200, 0), VIEWPORT);
+        assert_eq!(surface.pixel(100, 100), WHITE);
+    }
+
+    #[test]
+    fn test_palettes_swatches() {
+        let state = state();
+
+        let surface = palettes(&state);
+
+        assert_eq!(surface.pixel(12 + 3 * (SWATCH_SIZE + 2) + 8, 10), BLACK);
+        assert_eq!(surface.pixel(12 + 8, 10), WHITE);
+    }
+}
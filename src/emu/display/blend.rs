@@ -0,0 +1,177 @@
+use super::Frame;
+use crate::gameboy::Color;
+
+// how much of the previous frame is kept when decaying. the dmg's lcd takes a few frames to
+// settle, so a pixel turned off fades out over them rather than disappearing
+const DECAY_PERCENT: u32 = 60;
+const MIX_PERCENT: u32 = 50;
+
+// games that flicker sprites on alternate frames rely on the slow dmg lcd to blend them into
+// transparency, blending each frame with the ones before it gets the same effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    Off,
+    // half this frame and half the last one
+    Mix,
+    // each frame fades towards the new one, approximating the lcd's response time
+    Decay,
+}
+
+impl Blend {
+    pub const ALL: [Blend; 3] = [Blend::Off, Blend::Mix, Blend::Decay];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Blend::Off => "off",
+            Blend::Mix => "mix",
+            Blend::Decay => "decay",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Blend> {
+        Self::ALL.into_iter().find(|blend| blend.name() == name)
+    }
+
+    pub fn next(&self) -> Blend {
+        let index = Self::ALL.iter().position(|blend| blend == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// sits between the ppu's finished frame and the display showing it
+pub struct FrameBlender {
+    blend: Blend,
+    // the last frame for mix, or the last blended frame for decay
+    previous: Option<Frame>,
+}
+
+impl FrameBlender {
+    pub fn new(blend: Blend) -> Self {
+        Self {
+            blend,
+            previous: None,
+        }
+    }
+
+    pub fn blend(&self) -> Blend {
+        self.blend
+    }
+
+    pub fn set_blend(&mut self, blend: Blend) {
+        self.blend = blend;
+        self.previous = None;
+    }
+
+    pub fn apply(&mut self, frame: &Frame) -> Frame {
+        let previous = self
+            .previous
+            .take()
+            .filter(|previous| (previous.width, previous.height) == (frame.width, frame.height));
+        let Some(previous) = previous else {
+            if self.blend != Blend::Off {
+                self.previous = Some(frame.clone());
+            }
+            return frame.clone();
+        };
+
+        match self.blend {
+            Blend::Off => frame.clone(),
+            Blend::Mix => {
+                let blended = mix(&previous, frame, MIX_PERCENT);
+                self.previous = Some(frame.clone());
+                blended
+            }
+            Blend::Decay => {
+                let blended = mix(&previous, frame, DECAY_PERCENT);
+                self.previous = Some(blended.clone());
+                blended
+            }
+        }
+    }
+}
+
+fn mix(previous: &Frame, current: &Frame, previous_percent: u32) -> Frame {
+    let mut mixed = current.clone();
+    for (pixel, previous) in mixed.pixels.iter_mut().zip(&previous.pixels) {
+        *pixel = mix_color(*previous, *pixel, previous_percent);
+    }
+    mixed
+}
+
+fn mix_color(previous: Color, current: Color, previous_percent: u32) -> Color {
+    let channel = |previous: u8, current: u8| {
+        let mixed =
+            (previous as u32 * previous_percent + current as u32 * (100 - previous_percent) + 50)
+                / 100;
+        // rounding can leave a channel a step short forever, so finish the fade
+        if mixed as u8 == previous {
+            current
+        } else {
+            mixed as u8
+        }
+    };
+    Color::rgb(
+        channel(previous.r, current.r),
+        channel(previous.g, current.g),
+        channel(previous.b, current.b),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(color: Color) -> Frame {
+        let mut frame = Frame::new(2, 2);
+        frame.pixels.fill(color);
+        frame
+    }
+
+    #[test]
+    fn test_names() {
+        for blend in Blend::ALL {
+            assert_eq!(Blend::from_name(blend.name()), Some(blend));
+        }
+        assert_eq!(Blend::Decay.next(), Blend::Off);
+    }
+
+    #[test]
+    fn test_off() {
+        let mut blender = FrameBlender::new(Blend::Off);
+        blender.apply(&filled(Color::BLACK));
+
+        assert_eq!(blender.apply(&filled(Color::WHITE)), filled(Color::WHITE));
+    }
+
+    #[test]
+    fn test_mix_alternating_frames() {
+        let mut blender = FrameBlender::new(Blend::Mix);
+        let grey = Color::rgb(0x80, 0x80, 0x80);
+
+        assert_eq!(blender.apply(&filled(Color::BLACK)), filled(Color::BLACK));
+        assert_eq!(blender.apply(&filled(Color::WHITE)), filled(grey));
+        assert_eq!(blender.apply(&filled(Color::BLACK)), filled(grey));
+    }
+
+    #[test]
+    fn test_decay_fades_in() {
+        let mut blender = FrameBlender::new(Blend::Decay);
+        blender.apply(&filled(Color::BLACK));
+
+        let fades: Vec<u8> = (0..4)
+            .map(|_| blender.apply(&filled(Color::WHITE)).pixels[0].r)
+            .collect();
+        assert_eq!(fades, [102, 163, 200, 222]);
+        let settled = (0..20).map(|_| blender.apply(&filled(Color::WHITE))).last();
+        assert_eq!(settled, Some(filled(Color::WHITE)));
+    }
+
+    #[test]
+    fn test_size_change_starts_again() {
+        let mut blender = FrameBlender::new(Blend::Mix);
+        blender.apply(&filled(Color::BLACK));
+
+        let border = Frame::new(3, 3);
+        assert_eq!(blender.apply(&border), border);
+    }
+}
@@ -0,0 +1,357 @@
+use std::borrow::Cow;
+
+use super::Frame;
+use crate::gameboy::Color;
+
+// hqx's thresholds for two colors looking the same
+const Y_THRESHOLD: i32 = 0x30;
+const U_THRESHOLD: i32 = 0x07;
+const V_THRESHOLD: i32 = 0x06;
+const SCANLINE_BRIGHTNESS: u32 = 50;
+const LCD_GRID_BRIGHTNESS: u32 = 75;
+
+// upscaling filters run on the cpu before a frame is shown, so they can be tested without
+// a gpu. each has a fixed factor, the window's size is kept to a multiple of it and the
+// result is drawn at the largest whole number scale that fits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    None,
+    Scale2x,
+    Scale3x,
+    Hq2x,
+    Scanlines,
+    LcdGrid,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 6] = [
+        Filter::None,
+        Filter::Scale2x,
+        Filter::Scale3x,
+        Filter::Hq2x,
+        Filter::Scanlines,
+        Filter::LcdGrid,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::None => "none",
+            Filter::Scale2x => "scale2x",
+            Filter::Scale3x => "scale3x",
+            Filter::Hq2x => "hq2x",
+            Filter::Scanlines => "scanlines",
+            Filter::LcdGrid => "lcd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filter> {
+        Self::ALL.into_iter().find(|filter| filter.name() == name)
+    }
+
+    pub fn next(&self) -> Filter {
+        let index = Self::ALL.iter().position(|filter| filter == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn factor(&self) -> usize {
+        match self {
+            Filter::None => 1,
+            Filter::Scale2x | Filter::Hq2x => 2,
+            Filter::Scale3x | Filter::Scanlines | Filter::LcdGrid => 3,
+        }
+    }
+
+    // no filter hands the frame back as it is rather than copying it
+    pub fn apply<'a>(&self, frame: &'a Frame) -> Cow<'a, Frame> {
+        let scaled = match self {
+            Filter::None => return Cow::Borrowed(frame),
+            Filter::Scale2x => upscale(frame, 2, scale2x),
+            Filter::Scale3x => upscale(frame, 3, scale3x),
+            Filter::Hq2x => upscale(frame, 2, hq2x),
+            Filter::Scanlines => upscale(frame, 3, scanlines),
+            Filter::LcdGrid => upscale(frame, 3, lcd_grid),
+        };
+        Cow::Owned(scaled)
+    }
+}
+
+// each pixel becomes a factor x factor block worked out from the pixel and its 8 neighbours,
+// laid out a b c / d e f / g h i. the block is an array so there's no allocation per pixel
+fn upscale<const N: usize>(
+    frame: &Frame,
+    factor: usize,
+    block: fn(&[Color; 9]) -> [Color; N],
+) -> Frame {
+    let mut scaled = Frame::new(frame.width * factor, frame.height * factor);
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let colors = block(&neighbours(frame, x, y));
+            for (index, color) in colors.into_iter().enumerate() {
+                let scaled_x = x * factor + index % factor;
+                let scaled_y = y * factor + index / factor;
+                scaled.set_pixel(scaled_x, scaled_y, color);
+            }
+        }
+    }
+    scaled
+}
+
+// pixels off the edge repeat the nearest one
+fn neighbours(frame: &Frame, x: usize, y: usize) -> [Color; 9] {
+    let mut neighbours = [Color::BLACK; 9];
+    for (index, neighbour) in neighbours.iter_mut().enumerate() {
+        let neighbour_x = (x + index % 3).saturating_sub(1).min(frame.width - 1);
+        let neighbour_y = (y + index / 3).saturating_sub(1).min(frame.height - 1);
+        *neighbour = frame.pixel(neighbour_x, neighbour_y);
+    }
+    neighbours
+}
+
+fn scale2x(&[_, b, _, d, e, f, _, h, _]: &[Color; 9]) -> [Color; 4] {
+    if b == h || d == f {
+        return [e; 4];
+    }
+    [
+        if d == b { d } else { e },
+        if b == f { f } else { e },
+        if d == h { d } else { e },
+        if h == f { f } else { e },
+    ]
+}
+
+fn scale3x(&[a, b, c, d, e, f, g, h, i]: &[Color; 9]) -> [Color; 9] {
+    if b == h || d == f {
+        return [e; 9];
+    }
+    [
+        if d == b { d } else { e },
+        if (d == b && e != c) || (b == f && e != a) {
+            b
+        } else {
+            e
+        },
+        if b == f { f } else { e },
+        if (d == b && e != g) || (d == h && e != a) {
+            d
+        } else {
+            e
+        },
+        e,
+        if (b == f && e != i) || (h == f && e != c) {
+            f
+        } else {
+            e
+        },
+        if d == h { d } else { e },
+        if (d == h && e != i) || (h == f && e != g) {
+            h
+        } else {
+            e
+        },
+        if h == f { f } else { e },
+    ]
+}
+
+// hqx style: scale2x's edge rules, but comparing colors by how alike they look rather than
+// exactly and blending the corners along an edge instead of copying a neighbour
+fn hq2x(&[_, b, _, d, e, f, _, h, _]: &[Color; 9]) -> [Color; 4] {
+    if similar(b, h) || similar(d, f) {
+        return [e; 4];
+    }
+    let corner = |side1: Color, side2: Color| {
+        if similar(side1, side2) {
+            blend(&[(e, 2), (side1, 1), (side2, 1)])
+        } else {
+            e
+        }
+    };
+    [corner(d, b), corner(b, f), corner(d, h), corner(h, f)]
+}
+
+fn scanlines(&[_, _, _, _, e, _, _, _, _]: &[Color; 9]) -> [Color; 9] {
+    let dark = darken(e, SCANLINE_BRIGHTNESS);
+    [e, e, e, e, e, e, dark, dark, dark]
+}
+
+// the gaps between the dots of the lcd
+fn lcd_grid(&[_, _, _, _, e, _, _, _, _]: &[Color; 9]) -> [Color; 9] {
+    let dark = darken(e, LCD_GRID_BRIGHTNESS);
+    [e, e, dark, e, e, dark, dark, dark, dark]
+}
+
+fn yuv(color: Color) -> [i32; 3] {
+    let (r, g, b) = (color.r as i32, color.g as i32, color.b as i32);
+    [
+        (r + g + b) >> 2,
+        128 + ((r - b) >> 2),
+        128 + ((2 * g - r - b) >> 3),
+    ]
+}
+
+fn similar(color1: Color, color2: Color) -> bool {
+    let [y1, u1, v1] = yuv(color1);
+    let [y2, u2, v2] = yuv(color2);
+    (y1 - y2).abs() <= Y_THRESHOLD
+        && (u1 - u2).abs() <= U_THRESHOLD
+        && (v1 - v2).abs() <= V_THRESHOLD
+}
+
+fn blend(colors: &[(Color, u32)]) -> Color {
+    let total: u32 = colors.iter().map(|(_, weight)| weight).sum();
+    let channel = |get: fn(&Color) -> u8| {
+        let sum: u32 = colors
+            .iter()
+            .map(|(color, weight)| get(color) as u32 * weight)
+            .sum();
+        (sum / total) as u8
+    };
+    Color::rgb(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b))
+}
+
+fn darken(color: Color, percent: u32) -> Color {
+    let channel = |value: u8| (value as u32 * percent / 100) as u8;
+    Color::rgb(channel(color.r), channel(color.g), channel(color.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREY: Color = Color::rgb(0x80, 0x80, 0x80);
+
+    // a black diagonal with white all around it
+    fn diagonal() -> Frame {
+        let mut frame = Frame::new(5, 5);
+        for n in 1..4 {
+            frame.set_pixel(n, n, Color::BLACK);
+        }
+        frame
+    }
+
+    fn rows(frame: &Frame) -> Vec<String> {
+        (0..frame.height)
+            .map(|y| {
+                (0..frame.width)
+                    .map(|x| match frame.pixel(x, y) {
+                        Color::BLACK => '#',
+                        Color::WHITE => '.',
+                        _ => '+',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_names() {
+        for filter in Filter::ALL {
+            assert_eq!(Filter::from_name(filter.name()), Some(filter));
+        }
+        assert_eq!(Filter::from_name("blur"), None);
+        assert_eq!(Filter::LcdGrid.next(), Filter::None);
+    }
+
+    #[test]
+    fn test_factor_matches_output() {
+        let frame = Frame::new(4, 3);
+        for filter in Filter::ALL {
+            let filtered = filter.apply(&frame);
+            assert_eq!(filtered.width, 4 * filter.factor());
+            assert_eq!(filtered.height, 3 * filter.factor());
+        }
+    }
+
+    #[test]
+    fn test_none_borrows_the_frame() {
+        let frame = diagonal();
+        assert!(matches!(Filter::None.apply(&frame), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_scale2x_smooths_diagonals() {
+        assert_eq!(
+            rows(&Filter::Scale2x.apply(&diagonal())),
+            [
+                "..........",
+                "..........",
+                "..##......",
+                "..###.....",
+                "...###....",
+                "....###...",
+                ".....###..",
+                "......##..",
+                "..........",
+                "..........",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scale3x_smooths_diagonals() {
+        assert_eq!(
+            rows(&Filter::Scale3x.apply(&diagonal()))[3..12],
+            [
+                "...###.........",
+                "...###.........",
+                "...####........",
+                ".....####......",
+                "......###......",
+                "......####.....",
+                "........####...",
+                ".........###...",
+                ".........###...",
+            ]
+        );
+    }
+
+    // the same corners scale2x fills in are blended instead
+    #[test]
+    fn test_hq2x_blends_diagonals() {
+        assert_eq!(
+            rows(&Filter::Hq2x.apply(&diagonal())),
+            [
+                "..........",
+                "..........",
+                "..##......",
+                "..##+.....",
+                "...+##....",
+                "....##+...",
+                ".....+##..",
+                "......##..",
+                "..........",
+                "..........",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flat_areas_are_unchanged() {
+        let mut frame = Frame::new(2, 2);
+        frame.pixels.fill(GREY);
+        for filter in [Filter::Scale2x, Filter::Scale3x, Filter::Hq2x] {
+            assert!(filter
+                .apply(&frame)
+                .pixels
+                .iter()
+                .all(|color| *color == GREY));
+        }
+    }
+
+    #[test]
+    fn test_scanlines_and_lcd_grid() {
+        let mut frame = Frame::new(1, 1);
+        frame.pixels.fill(GREY);
+        let dark = Color::rgb(0x40, 0x40, 0x40);
+        let grid = Color::rgb(0x60, 0x60, 0x60);
+
+        let scanlines = Filter::Scanlines.apply(&frame);
+        assert_eq!(scanlines.pixels[..6], [GREY; 6]);
+        assert_eq!(scanlines.pixels[6..], [dark; 3]);
+
+        let lcd = Filter::LcdGrid.apply(&frame);
+        assert_eq!(
+            lcd.pixels,
+            [GREY, GREY, grid, GREY, GREY, grid, grid, grid, grid]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+};
+
+use crate::gameboy::Color;
+
+// an image held in memory. screenshots are saved from it and screenshot tests compare it
+// against a reference png
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::WHITE; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    // nearest neighbour, each pixel becomes a factor x factor block
+    pub fn scale(&self, factor: usize) -> Frame {
+        let mut scaled = Frame::new(self.width * factor, self.height * factor);
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                scaled.set_pixel(x, y, self.pixel(x / factor, y / factor));
+            }
+        }
+        scaled
+    }
+
+    pub fn save_png(&self, path: &str) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self.pixels.iter().flat_map(Color::rgba).collect();
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(())
+    }
+
+    // any png, paletted, grey and 16 bit images are converted to 8 bit colors
+    pub fn load_png(path: &str) -> Self {
+        let file = File::open(path).unwrap_or_else(|_| panic!("no file found {}", path));
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .unwrap_or_else(|error| panic!("failed to read {}: {}", path, error));
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut data)
+            .unwrap_or_else(|error| panic!("failed to read {}: {}", path, error));
+
+        let pixels = data[..info.buffer_size()]
+            .chunks(info.color_type.samples())
+            .map(|sample| match *sample {
+                [grey] | [grey, _] => Color::rgb(grey, grey, grey),
+                [r, g, b] => Color::rgb(r, g, b),
+                [r, g, b, a] => Color { r, g, b, a },
+                _ => panic!("unsupported png color type {:?}", info.color_type),
+            })
+            .collect();
+
+        Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        }
+    }
+
+    // the number of pixels where a color channel is more than tolerance away from the
+    // reference, alpha is ignored
+    pub fn compare(&self, reference: &Frame, tolerance: u8) -> usize {
+        if (self.width, self.height) != (reference.width, reference.height) {
+            panic!(
+                "frame is {}x{} but the reference is {}x{}",
+                self.width, self.height, reference.width, reference.height
+            );
+        }
+
+        self.pixels
+            .iter()
+            .zip(reference.pixels.iter())
+            .filter(|(color, expected)| {
+                color.r.abs_diff(expected.r) > tolerance
+                    || color.g.abs_diff(expected.g) > tolerance
+                    || color.b.abs_diff(expected.b) > tolerance
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frame() -> Frame {
+        let mut frame = Frame::new(3, 2);
+        frame.set_pixel(0, 0, Color::BLACK);
+        frame.set_pixel(2, 1, Color::rgb(0x12, 0x34, 0x56));
+        frame
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let path = std::env::temp_dir().join("garlickboy_test_png_round_trip.png");
+        let path = path.to_str().unwrap();
+        let frame = test_frame();
+
+        frame.save_png(path).unwrap();
+        let loaded = Frame::load_png(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, frame);
+    }
+
+    #[test]
+    fn test_scale() {
+        let scaled = test_frame().scale(2);
+
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert_eq!(scaled.pixel(1, 1), Color::BLACK);
+        assert_eq!(scaled.pixel(2, 1), Color::WHITE);
+        assert_eq!(scaled.pixel(5, 3), Color::rgb(0x12, 0x34, 0x56));
+    }
+
+    #[test]
+    fn test_compare_tolerance() {
+        let frame = test_frame();
+        let mut reference = test_frame();
+        reference.set_pixel(2, 1, Color::rgb(0x12, 0x34, 0x58));
+        reference.set_pixel(1, 0, Color::rgb(0xFF, 0xF0, 0xFF));
+
+        assert_eq!(frame.compare(&reference, 0), 2);
+        assert_eq!(frame.compare(&reference, 2), 1);
+        assert_eq!(frame.compare(&reference, 0x0F), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "frame is 3x2 but the reference is 2x2")]
+    fn test_compare_different_sizes() {
+        test_frame().compare(&Frame::new(2, 2), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use super::{Display, Frame, VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::gameboy::Color;
+
+// keeps the screen in memory rather than showing it, for tests and batch runs
+pub struct HeadlessDisplay {
+    frame: Frame,
+}
+
+impl HeadlessDisplay {
+    pub fn new() -> Self {
+        Self {
+            frame: Frame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize),
+        }
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+impl Default for HeadlessDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for HeadlessDisplay {
+    fn present(&mut self) {}
+
+    fn off(&mut self) {
+        self.frame.pixels.fill(Color::WHITE);
+    }
+
+    fn draw_pixel(&mut self, x: u8, y: u8, color: Color) {
+        self.frame.set_pixel(x as usize, y as usize, color);
+    }
+
+    // only the game screen is kept
+    fn draw_border(&mut self, _border: &[Color]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_and_off() {
+        let mut display = HeadlessDisplay::new();
+
+        display.draw_pixel(159, 143, Color::BLACK);
+        assert_eq!(display.frame().pixel(159, 143), Color::BLACK);
+
+        display.off();
+        assert_eq!(display.frame().pixel(159, 143), Color::WHITE);
+    }
+}
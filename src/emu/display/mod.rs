@@ -1,21 +1,47 @@
+mod blend;
+mod filter;
+mod frame;
+mod headless;
+#[cfg(feature = "sdl")]
 mod sdl;
+mod terminal;
 
-use sdl2::{pixels::Color, EventPump};
+pub use self::blend::{Blend, FrameBlender};
+pub use self::filter::Filter;
+pub use self::frame::Frame;
+pub use self::headless::HeadlessDisplay;
+#[cfg(feature = "sdl")]
+pub(crate) use self::sdl::SdlDisplay;
+use self::terminal::TerminalDisplay;
+use crate::gameboy::Color;
 
-use self::sdl::SdlDisplay;
-
-pub const VIDEO_SCALE: u32 = 3;
+pub const MAX_SCALE: u32 = 8;
 pub const VIDEO_WIDTH: u8 = 160;
 pub const VIDEO_HEIGHT: u8 = 144;
+pub const BORDER_WIDTH: u32 = 256;
+pub const BORDER_HEIGHT: u32 = 224;
+pub const BORDER_SCREEN_X: u32 = 48;
+pub const BORDER_SCREEN_Y: u32 = 40;
 
 pub trait Display {
     fn present(&mut self);
     fn off(&mut self);
     fn draw_pixel(&mut self, x: u8, y: u8, color: Color);
+    // sgb border, BORDER_WIDTH x BORDER_HEIGHT pixels with the screen drawn over it
+    fn draw_border(&mut self, border: &[Color]);
+}
+
+#[cfg(feature = "sdl")]
+pub(crate) fn new_sdl_display(
+    sdl: &sdl2::Sdl,
+    scale: u32,
+    filter: Filter,
+    blend: Blend,
+    fullscreen: bool,
+) -> SdlDisplay {
+    SdlDisplay::new(sdl, scale, filter, blend, fullscreen)
 }
 
-pub fn new_sdl_display() -> (Box<dyn Display>, EventPump) {
-    let sdl = sdl2::init().expect("failed to init sdl2");
-    let event_pump = sdl.event_pump().expect("failed to get event_pump");
-    (SdlDisplay::new(sdl), event_pump)
+pub fn new_terminal_display(blend: Blend) -> Box<dyn Display> {
+    Box::new(TerminalDisplay::new(Box::new(std::io::stdout()), blend))
 }
@@ -1,56 +1,122 @@
 use std::cell::RefCell;
 
 use sdl2::{
-    pixels::{Color, PixelFormat},
+    pixels::{self, PixelFormatEnum},
+    rect::Rect,
     render::{Canvas, Texture, TextureCreator},
+    video::FullscreenType,
     Sdl,
 };
 
-use super::{Display, VIDEO_HEIGHT, VIDEO_SCALE, VIDEO_WIDTH};
+use super::{
+    Blend, Color, Display, Filter, Frame, FrameBlender, BORDER_HEIGHT, BORDER_SCREEN_X,
+    BORDER_SCREEN_Y, BORDER_WIDTH, MAX_SCALE, VIDEO_HEIGHT, VIDEO_WIDTH,
+};
 
 pub(crate) struct SdlDisplay {
     canvas: Canvas<sdl2::video::Window>,
     texture_creator: TextureCreator<sdl2::video::WindowContext>,
     texture: RefCell<Texture<'static>>,
-    data: Vec<u32>,
-    width: u8,
+    // the whole picture, the screen plus the sgb border once there is one
+    frame: Frame,
+    screen_x: u32,
+    screen_y: u32,
+    scale: u32,
+    blender: FrameBlender,
+    filter: Filter,
 }
 
 impl Display for SdlDisplay {
+    // the frame is blended with the ones before it and filtered, then drawn at the largest
+    // whole number scale that fits the window with black bars around it
     fn present(&mut self) {
+        let blended = self.blender.apply(&self.frame);
+        let filtered = self.filter.apply(&blended);
+        let (width, height) = (filtered.width as u32, filtered.height as u32);
+        let query = self.texture.borrow().query();
+        if (query.width, query.height) != (width, height) {
+            let texture = Self::create_texture(&self.texture_creator, width, height);
+            self.texture.replace(texture);
+        }
+
+        let data: Vec<u8> = filtered
+            .pixels
+            .iter()
+            .flat_map(|color| color.rgba())
+            .collect();
         let mut texture = self.texture.borrow_mut();
         texture
-            .update(None, self.data_raw(), (self.width as u32 * 4) as usize)
+            .update(None, &data, (width * 4) as usize)
             .expect("failed to update screen texture");
-        self.canvas.copy(&texture, None, None).unwrap();
+        let window = self
+            .canvas
+            .output_size()
+            .expect("failed to get window size");
+        self.canvas.set_draw_color(pixels::Color::BLACK);
+        self.canvas.clear();
+        self.canvas
+            .copy(&texture, None, letterbox(window, (width, height)))
+            .unwrap();
         self.canvas.present();
     }
 
     fn off(&mut self) {
-        self.data.fill(u32::MAX)
+        for y in 0..VIDEO_HEIGHT {
+            for x in 0..VIDEO_WIDTH {
+                self.draw_pixel(x, y, Color::WHITE);
+            }
+        }
     }
 
     fn draw_pixel(&mut self, x: u8, y: u8, color: Color) {
-        let y = y as u32;
-        let x = x as u32;
-        let width = self.width as u32;
-        self.data[(y * width + x) as usize] = color
-            .to_u32(&PixelFormat::try_from(self.texture_creator.default_pixel_format()).unwrap());
+        self.frame.set_pixel(
+            (self.screen_x + x as u32) as usize,
+            (self.screen_y + y as u32) as usize,
+            color,
+        );
+    }
+
+    // the first border switches the window over to the larger sgb layout
+    fn draw_border(&mut self, border: &[Color]) {
+        if self.frame.width != BORDER_WIDTH as usize {
+            self.frame = Frame::new(BORDER_WIDTH as usize, BORDER_HEIGHT as usize);
+            self.screen_x = BORDER_SCREEN_X;
+            self.screen_y = BORDER_SCREEN_Y;
+            self.resize_window();
+        }
+
+        let screen_x = self.screen_x..self.screen_x + VIDEO_WIDTH as u32;
+        let screen_y = self.screen_y..self.screen_y + VIDEO_HEIGHT as u32;
+        for (index, color) in border.iter().enumerate() {
+            let x = index as u32 % BORDER_WIDTH;
+            let y = index as u32 / BORDER_WIDTH;
+            if !screen_x.contains(&x) || !screen_y.contains(&y) {
+                self.frame.pixels[index] = *color;
+            }
+        }
     }
 }
 
 impl SdlDisplay {
-    pub(crate) fn new(sdl: Sdl) -> Box<dyn Display> {
+    pub(crate) fn new(
+        sdl: &Sdl,
+        scale: u32,
+        filter: Filter,
+        blend: Blend,
+        fullscreen: bool,
+    ) -> Self {
         let video_width = VIDEO_WIDTH as u32;
         let video_height = VIDEO_HEIGHT as u32;
+        let window_scale = window_scale(scale, filter.factor() as u32);
         let video = sdl.video().expect("failed to get video subsystem");
         let window = video
             .window(
                 "GarlickBoy",
-                video_width * VIDEO_SCALE,
-                video_height * VIDEO_SCALE,
+                video_width * window_scale,
+                video_height * window_scale,
             )
             .position_centered()
+            .resizable()
             .opengl()
             .build()
             .expect("failed to build window");
@@ -60,28 +126,145 @@ impl SdlDisplay {
             .build()
             .expect("failed to convert window into canvas");
         let texture_creator = canvas.texture_creator();
-        let texture = texture_creator
-            .create_texture_target(
-                texture_creator.default_pixel_format(),
-                video_width,
-                video_height,
-            )
-            .unwrap();
-
-        let texture = unsafe { std::mem::transmute::<_, Texture<'static>>(texture) };
+        let texture = Self::create_texture(&texture_creator, video_width, video_height);
 
-        let mut display = Box::new(Self {
+        let mut display = Self {
             canvas,
             texture_creator,
             texture: RefCell::new(texture),
-            data: vec![0; (video_width * video_height) as usize],
-            width: VIDEO_WIDTH,
-        });
-        display.off();
+            frame: Frame::new(video_width as usize, video_height as usize),
+            screen_x: 0,
+            screen_y: 0,
+            scale,
+            blender: FrameBlender::new(blend),
+            filter,
+        };
+        if fullscreen {
+            display.toggle_fullscreen();
+        }
         display
     }
 
-    fn data_raw(&self) -> &[u8] {
-        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * 4) }
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    // resizes the window to a multiple of the picture, unless it's fullscreen
+    pub fn set_scale(&mut self, scale: u32) {
+        self.scale = scale.clamp(1, MAX_SCALE);
+        self.resize_window();
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.resize_window();
+    }
+
+    pub fn blend(&self) -> Blend {
+        self.blender.blend()
+    }
+
+    pub fn set_blend(&mut self, blend: Blend) {
+        self.blender.set_blend(blend);
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        window
+            .set_fullscreen(fullscreen)
+            .expect("failed to toggle fullscreen");
+    }
+
+    fn create_texture(
+        texture_creator: &TextureCreator<sdl2::video::WindowContext>,
+        width: u32,
+        height: u32,
+    ) -> Texture<'static> {
+        let texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .unwrap();
+
+        unsafe { std::mem::transmute::<_, Texture<'static>>(texture) }
+    }
+
+    fn resize_window(&mut self) {
+        let scale = window_scale(self.scale, self.filter.factor() as u32);
+        let width = self.frame.width as u32 * scale;
+        let height = self.frame.height as u32 * scale;
+        let window = self.canvas.window_mut();
+        if window.fullscreen_state() == FullscreenType::Off {
+            window
+                .set_size(width, height)
+                .expect("failed to resize window");
+        }
+    }
+}
+
+// the scale rounded up to a multiple of the filter's factor, so the filtered picture fills
+// the window at a whole number scale, eg 4x rather than 3x with a 2x filter
+fn window_scale(scale: u32, factor: u32) -> u32 {
+    scale.div_ceil(factor) * factor
+}
+
+// the largest whole number scale of the picture that fits the window, centered so any space
+// left over is split evenly either side
+fn letterbox((window_width, window_height): (u32, u32), (width, height): (u32, u32)) -> Rect {
+    let scale = (window_width / width).min(window_height / height).max(1);
+    let (scaled_width, scaled_height) = (width * scale, height * scale);
+    Rect::new(
+        (window_width as i32 - scaled_width as i32) / 2,
+        (window_height as i32 - scaled_height as i32) / 2,
+        scaled_width,
+        scaled_height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letterbox() {
+        assert_eq!(letterbox((480, 432), (160, 144)), Rect::new(0, 0, 480, 432));
+        // a wide window gets wider bars at the sides than at the top and bottom
+        assert_eq!(
+            letterbox((1920, 1080), (160, 144)),
+            Rect::new(400, 36, 1120, 1008)
+        );
+        // an odd size stays at a whole number scale rather than stretching
+        assert_eq!(
+            letterbox((500, 450), (160, 144)),
+            Rect::new(10, 9, 480, 432)
+        );
+        assert_eq!(
+            letterbox((100, 100), (160, 144)),
+            Rect::new(-30, -22, 160, 144)
+        );
+    }
+
+    #[test]
+    fn test_letterbox_filtered_at_scale_3() {
+        // a 2x filter's 320x288 isn't stretched by 1.5 into a 3x window
+        assert_eq!(
+            letterbox((480, 432), (320, 288)),
+            Rect::new(80, 72, 320, 288)
+        );
+        // the window is opened at 4x instead, which it fills
+        let scale = window_scale(3, Filter::Scale2x.factor() as u32);
+        assert_eq!(scale, 4);
+        assert_eq!(
+            letterbox((160 * scale, 144 * scale), (320, 288)),
+            Rect::new(0, 0, 640, 576)
+        );
+        assert_eq!(window_scale(3, Filter::Scale3x.factor() as u32), 3);
+        assert_eq!(window_scale(3, Filter::None.factor() as u32), 3);
     }
 }
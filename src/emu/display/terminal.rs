@@ -0,0 +1,129 @@
+use std::{fmt::Write as _, io::Write};
+
+use super::{Blend, Display, Frame, FrameBlender, VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::gameboy::Color;
+
+const UPPER_HALF_BLOCK: char = '▀';
+const COLUMNS: usize = VIDEO_WIDTH as usize;
+const ROWS: usize = VIDEO_HEIGHT as usize / 2;
+
+// draws the screen in a 24 bit color terminal. each character is two pixels, the upper half
+// block's foreground is the top pixel and its background the bottom one. only the cells that
+// changed since the last frame are written, which keeps it usable over ssh
+pub struct TerminalDisplay {
+    frame: Frame,
+    // the top and bottom colors each cell is showing, none until it's first drawn
+    cells: Vec<Option<(Color, Color)>>,
+    blender: FrameBlender,
+    output: Box<dyn Write>,
+}
+
+impl TerminalDisplay {
+    pub fn new(output: Box<dyn Write>, blend: Blend) -> Self {
+        let mut display = Self {
+            frame: Frame::new(COLUMNS, VIDEO_HEIGHT as usize),
+            cells: vec![None; COLUMNS * ROWS],
+            blender: FrameBlender::new(blend),
+            output,
+        };
+        // clear the screen and hide the cursor
+        display.write("\x1b[2J\x1b[?25l");
+        display
+    }
+
+    fn draw(&mut self) -> String {
+        let frame = self.blender.apply(&self.frame);
+        let mut output = String::new();
+        let mut cursor = None;
+        let mut colors = None;
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let cell = (
+                    frame.pixel(column, row * 2),
+                    frame.pixel(column, row * 2 + 1),
+                );
+                let index = row * COLUMNS + column;
+                if self.cells[index] == Some(cell) {
+                    continue;
+                }
+                self.cells[index] = Some(cell);
+
+                // the cursor only needs moving when cells were skipped
+                if cursor != Some((row, column)) {
+                    let _ = write!(output, "\x1b[{};{}H", row + 1, column + 1);
+                }
+                if colors != Some(cell) {
+                    let (top, bottom) = cell;
+                    let _ = write!(
+                        output,
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+                    );
+                }
+                output.push(UPPER_HALF_BLOCK);
+                cursor = Some((row, column + 1));
+                colors = Some(cell);
+            }
+        }
+        if !output.is_empty() {
+            output.push_str("\x1b[0m");
+        }
+        output
+    }
+
+    fn write(&mut self, output: &str) {
+        self.output
+            .write_all(output.as_bytes())
+            .and_then(|_| self.output.flush())
+            .expect("failed to write to the terminal");
+    }
+}
+
+impl Display for TerminalDisplay {
+    fn present(&mut self) {
+        let output = self.draw();
+        if !output.is_empty() {
+            self.write(&output);
+        }
+    }
+
+    fn off(&mut self) {
+        self.frame.pixels.fill(Color::WHITE);
+    }
+
+    fn draw_pixel(&mut self, x: u8, y: u8, color: Color) {
+        self.frame.set_pixel(x as usize, y as usize, color);
+    }
+
+    // only the game screen fits in a terminal
+    fn draw_border(&mut self, _border: &[Color]) {}
+}
+
+impl Drop for TerminalDisplay {
+    // leave the cursor below the screen with the colors reset
+    fn drop(&mut self) {
+        let output = format!("\x1b[0m\x1b[{};1H\x1b[?25h", ROWS + 1);
+        let _ = self.output.write_all(output.as_bytes());
+        let _ = self.output.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_only_changed_cells() {
+        let mut display = TerminalDisplay::new(Box::new(std::io::sink()), Blend::Off);
+
+        let first = display.draw();
+        assert_eq!(first.matches(UPPER_HALF_BLOCK).count(), COLUMNS * ROWS);
+        assert_eq!(display.draw(), "");
+
+        display.draw_pixel(3, 1, Color::BLACK);
+        assert_eq!(
+            display.draw(),
+            "\x1b[1;4H\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m▀\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use super::{
+    audio::{new_mixer, wav::WavRecorder, EMULATED_RATE},
+    display::{HeadlessDisplay, VIDEO_HEIGHT, VIDEO_WIDTH},
+    options::Options,
+    palettes::{self, PALETTES_CONFIG},
+    recorder::Recorder,
+    rom::Rom,
+    screenshots::Screenshots,
+};
+use crate::gameboy::Gameboy;
+
+// blargg's test roms report through cartridge ram, a status at 0xA000 that reads 0x80 while
+// running and 0 once passed, a signature after it and then the text they print
+const BLARGG_STATUS: u16 = 0xA000;
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const BLARGG_TEXT: u16 = 0xA004;
+const BLARGG_TEXT_END: u16 = 0xBFFF;
+const BLARGG_RUNNING: u8 = 0x80;
+// the dmg_sound roms take around 20 seconds between them
+const BLARGG_FRAMES: u32 = 60 * 60;
+
+// runs a rom for a number of frames without a window, saving every nth frame or recording
+// them and the sound if asked
+pub fn run_headless(options: &Options, frames: u32) {
+    let rom = Rom::new(&options.rom);
+    let screenshots = Screenshots::new(
+        &options.screenshot_dir,
+        rom.title(),
+        options.screenshot_scale,
+    );
+    let color_schemes = palettes::rom_color_schemes(&rom, PALETTES_CONFIG);
+    let mut gameboy = Gameboy::new();
+    gameboy.load_rom(&rom);
+    gameboy.set_color_scheme(color_schemes[0].clone());
+
+    let mut recorder = options.record.as_ref().map(|path| {
+        Recorder::new(path, VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize)
+            .unwrap_or_else(|error| panic!("unable to create {}: {}", path, error))
+    });
+    let mut wav = options.wav.as_ref().map(|path| {
+        WavRecorder::new(path, options.stems)
+            .unwrap_or_else(|error| panic!("unable to create {}: {}", path, error))
+    });
+    if wav.is_some() {
+        gameboy.set_sample_rate(EMULATED_RATE);
+        gameboy.set_stems(options.stems);
+        gameboy.set_mixer(new_mixer(options));
+    }
+    let mut display = HeadlessDisplay::new();
+    for frame in 1..=frames {
+        gameboy.run_frames(1, &mut display);
+        if options.dump_every.is_some_and(|every| frame % every == 0) {
+            let path = screenshots
+                .save_numbered(display.frame(), frame)
+                .unwrap_or_else(|error| panic!("unable to save frame {}: {}", frame, error));
+            println!("saved frame {}", path);
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder
+                .write_frame(display.frame())
+                .unwrap_or_else(|error| panic!("failed to write {}: {}", recorder.path(), error));
+        }
+        if let Some(wav) = wav.as_mut() {
+            wav.write(&gameboy.take_samples(), &gameboy.take_stems())
+                .unwrap_or_else(|error| panic!("failed to write {}: {}", wav.path(), error));
+        }
+    }
+    if let Some(recorder) = recorder {
+        println!(
+            "saved recording {} ({} frames)",
+            recorder.path(),
+            recorder.frames()
+        );
+        recorder.finish().expect("failed to write the recording");
+    }
+    if let Some(wav) = wav {
+        println!(
+            "saved audio recording {} ({:.1} seconds)",
+            wav.path(),
+            wav.seconds()
+        );
+        wav.finish().expect("failed to write the audio recording");
+    }
+}
+
+// runs one of blargg's test roms, eg dmg_sound's singles, until it reports a result or the
+// frame limit from --headless runs out. true if it passed
+pub fn run_blargg(options: &Options) -> bool {
+    let rom = Rom::new(&options.rom);
+    let mut gameboy = Gameboy::new();
+    gameboy.load_rom(&rom);
+
+    let frames = options.headless_frames.unwrap_or(BLARGG_FRAMES);
+    let mut display = HeadlessDisplay::new();
+    for _ in 0..frames {
+        gameboy.run_frames(1, &mut display);
+        if let Some((status, text)) = blargg_result(|address| gameboy.read_memory(address)) {
+            println!("{}", text.trim_end());
+            return status == 0;
+        }
+    }
+    println!("{} didn't finish within {} frames", options.rom, frames);
+    false
+}
+
+// the status and text once the rom is done, none while it's running or hasn't started
+fn blargg_result(mut read: impl FnMut(u16) -> u8) -> Option<(u8, String)> {
+    let signature = [1, 2, 3].map(|offset| read(BLARGG_STATUS + offset));
+    let status = read(BLARGG_STATUS);
+    if signature != BLARGG_SIGNATURE || status == BLARGG_RUNNING {
+        return None;
+    }
+    let text = (BLARGG_TEXT..=BLARGG_TEXT_END)
+        .map(&mut read)
+        .take_while(|byte| *byte != 0)
+        .map(char::from)
+        .collect();
+    Some((status, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blargg_memory(status: u8, text: &str) -> Vec<u8> {
+        let mut memory = vec![status];
+        memory.extend(BLARGG_SIGNATURE);
+        memory.extend(text.bytes());
+        memory.push(0);
+        memory
+    }
+
+    fn result(memory: &[u8]) -> Option<(u8, String)> {
+        blargg_result(|address| {
+            memory
+                .get((address - BLARGG_STATUS) as usize)
+                .copied()
+                .unwrap_or(0xFF)
+        })
+    }
+
+    #[test]
+    fn test_blargg_result() {
+        assert_eq!(result(&[0; 4]), None);
+        assert_eq!(
+            result(&blargg_memory(BLARGG_RUNNING, "01-registers\n")),
+            None
+        );
+        assert_eq!(
+            result(&blargg_memory(0, "01-registers\n\nPassed\n")),
+            Some((0, "01-registers\n\nPassed\n".to_string()))
+        );
+        assert_eq!(
+            result(&blargg_memory(2, "Failed #2\n")),
+            Some((2, "Failed #2\n".to_string()))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use super::display::{Blend, Filter, MAX_SCALE};
+
+pub const DEFAULT_ROM: &str = "data/Tetris.gb";
+const DEFAULT_SCALE: u32 = 3;
+const DEFAULT_SCREENSHOT_DIR: &str = "screenshots";
+const USAGE: &str = "usage: garlickboy [rom] [--screenshot-dir DIR] [--screenshot-scale N] \
+                     [--headless FRAMES] [--dump-every N] [--record FILE] [--wav FILE] [--stems] \
+                     [--terminal] [--scale N] \
+                     [--filter none|scale2x|scale3x|hq2x|scanlines|lcd] [--blend off|mix|decay] \
+                     [--fullscreen] [--no-audio] [--audio-sync] [--no-high-pass] [--blargg]";
+
+// command line options, eg
+//
+// garlickboy data/Tetris.gb --headless 600 --dump-every 60 --screenshot-dir dumps
+// garlickboy data/Tetris.gb --record tetris.y4m
+// garlickboy data/Tetris.gb --headless 3600 --wav tetris.wav --stems
+// garlickboy data/Tetris.gb --terminal
+// garlickboy data/Tetris.gb --scale 4 --filter scale2x --blend decay --fullscreen
+// garlickboy data/Tetris.gb --audio-sync --no-high-pass
+// garlickboy dmg_sound/rom_singles/01-registers.gb --blargg
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub rom: String,
+    pub screenshot_dir: String,
+    pub screenshot_scale: usize,
+    // run this many frames without a window instead of opening one
+    pub headless_frames: Option<u32>,
+    // save every nth frame to the screenshot dir during a headless run
+    pub dump_every: Option<u32>,
+    // record a y4m video from the first frame
+    pub record: Option<String>,
+    // record the sound from the first frame
+    pub wav: Option<String>,
+    // record each sound channel into its own wav too
+    pub stems: bool,
+    // play in the terminal instead of a window
+    pub terminal: bool,
+    // window size as a multiple of the screen, rounded up to a multiple of the filter's factor
+    pub scale: u32,
+    pub filter: Filter,
+    // blending frames together like the dmg's slow lcd
+    pub blend: Blend,
+    pub fullscreen: bool,
+    // play sound through the default audio device
+    pub audio: bool,
+    // pace frames by the audio device instead of the clock
+    pub audio_sync: bool,
+    // block dc in the sound like the dmg's output capacitor, the mixer can turn it off later
+    pub high_pass: bool,
+    // run one of blargg's test roms headless and exit with its result
+    pub blargg: bool,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = Self {
+            rom: DEFAULT_ROM.to_string(),
+            screenshot_dir: DEFAULT_SCREENSHOT_DIR.to_string(),
+            screenshot_scale: 1,
+            headless_frames: None,
+            dump_every: None,
+            record: None,
+            wav: None,
+            stems: false,
+            terminal: false,
+            scale: DEFAULT_SCALE,
+            filter: Filter::None,
+            blend: Blend::Off,
+            fullscreen: false,
+            audio: true,
+            audio_sync: false,
+            high_pass: true,
+            blargg: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("missing value for {}\n{}", arg, USAGE))
+            };
+            match arg.as_str() {
+                "--screenshot-dir" => options.screenshot_dir = value(),
+                "--screenshot-scale" => options.screenshot_scale = number(&value()),
+                "--headless" => options.headless_frames = Some(number(&value())),
+                "--dump-every" => options.dump_every = Some(number(&value())),
+                "--record" => options.record = Some(value()),
+                "--wav" => options.wav = Some(value()),
+                "--stems" => options.stems = true,
+                "--terminal" => options.terminal = true,
+                "--scale" => options.scale = number(&value()),
+                "--filter" => options.filter = filter(&value()),
+                "--blend" => options.blend = blend(&value()),
+                "--fullscreen" => options.fullscreen = true,
+                "--no-audio" => options.audio = false,
+                "--audio-sync" => options.audio_sync = true,
+                "--no-high-pass" => options.high_pass = false,
+                "--blargg" => options.blargg = true,
+                arg if arg.starts_with("--") => panic!("unknown option {}\n{}", arg, USAGE),
+                _ => options.rom = arg,
+            }
+        }
+
+        if options.screenshot_scale == 0 || options.dump_every == Some(0) {
+            panic!("--screenshot-scale and --dump-every must be at least 1");
+        }
+        if !(1..=MAX_SCALE).contains(&options.scale) {
+            panic!("--scale must be between 1 and {}", MAX_SCALE);
+        }
+        if options.audio_sync && !options.audio {
+            panic!("--audio-sync needs audio, it can't be used with --no-audio");
+        }
+        options
+    }
+}
+
+fn filter(name: &str) -> Filter {
+    Filter::from_name(name).unwrap_or_else(|| panic!("unknown filter {}\n{}", name, USAGE))
+}
+
+fn blend(name: &str) -> Blend {
+    Blend::from_name(name).unwrap_or_else(|| panic!("unknown blend {}\n{}", name, USAGE))
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("expected a number but got {}\n{}", value, USAGE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Options {
+        Options::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_defaults() {
+        let options = parse("");
+
+        assert_eq!(options.rom, DEFAULT_ROM);
+        assert_eq!(options.screenshot_dir, DEFAULT_SCREENSHOT_DIR);
+        assert_eq!(options.screenshot_scale, 1);
+        assert_eq!(options.headless_frames, None);
+        assert_eq!(options.record, None);
+        assert_eq!(options.wav, None);
+        assert!(!options.stems);
+        assert!(!options.terminal);
+        assert_eq!(options.scale, DEFAULT_SCALE);
+        assert_eq!(options.filter, Filter::None);
+        assert_eq!(options.blend, Blend::Off);
+        assert!(options.audio);
+        assert!(!options.audio_sync);
+        assert!(options.high_pass);
+        assert!(!options.blargg);
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(
+            "--headless 600 game.gb --dump-every 60 --screenshot-dir out --screenshot-scale 3 \
+             --record out.y4m --wav out.wav --stems --scale 4 --filter hq2x --blend mix --fullscreen --audio-sync \
+             --no-high-pass --blargg",
+        );
+
+        assert_eq!(
+            options,
+            Options {
+                rom: "game.gb".to_string(),
+                screenshot_dir: "out".to_string(),
+                screenshot_scale: 3,
+                headless_frames: Some(600),
+                dump_every: Some(60),
+                record: Some("out.y4m".to_string()),
+                wav: Some("out.wav".to_string()),
+                stems: true,
+                terminal: false,
+                scale: 4,
+                filter: Filter::Hq2x,
+                blend: Blend::Mix,
+                fullscreen: true,
+                audio: true,
+                audio_sync: true,
+                high_pass: false,
+                blargg: true,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown option --fast")]
+    fn test_unknown_option() {
+        parse("--fast");
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown filter blur")]
+    fn test_unknown_filter() {
+        parse("--filter blur");
+    }
+
+    #[test]
+    #[should_panic(expected = "--audio-sync needs audio")]
+    fn test_audio_sync_without_audio() {
+        parse("--no-audio --audio-sync");
+    }
+
+    #[test]
+    #[should_panic(expected = "missing value for --headless")]
+    fn test_missing_value() {
+        parse("--headless");
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::Path};
+
+use super::rom::Rom;
+use crate::gameboy::{compatibility_scheme, Color, ColorScheme, Shades};
+
+pub const PALETTES_CONFIG: &str = "data/palettes.cfg";
+
+// custom palettes are read from a simple ini style file, eg
+//
+// [autumn]
+// background = FFF6D3 F9A875 EB6B6F 7C3F58
+// object0 = FFFFFF F9A875 EB6B6F 000000
+//
+// object0/object1 default to the background colors when not given. the file is edited by
+// hand, so a mistake in it only skips the palette it's in with a warning
+pub fn load_color_schemes(path: &str) -> Vec<ColorScheme> {
+    let mut schemes = ColorScheme::presets();
+    if Path::new(path).exists() {
+        match fs::read_to_string(path) {
+            Ok(config) => {
+                let (custom, warnings) = parse_color_schemes(&config);
+                for warning in warnings {
+                    eprintln!("{}: {}", path, warning);
+                }
+                schemes.extend(custom);
+            }
+            Err(error) => eprintln!("unable to read {}: {}", path, error),
+        }
+    }
+    schemes
+}
+
+// like a cgb, dmg games start colorized based on their title
+pub fn rom_color_schemes(rom: &Rom, path: &str) -> Vec<ColorScheme> {
+    let mut schemes = load_color_schemes(path);
+    if !rom.is_cgb() {
+        let scheme = compatibility_scheme(&rom.title_bytes(), rom.is_nintendo());
+        schemes.insert(0, scheme);
+    }
+    schemes
+}
+
+struct Section {
+    name: String,
+    background: Option<Shades>,
+    object0: Option<Shades>,
+    object1: Option<Shades>,
+    // the first bad line, the section is skipped if there is one
+    error: Option<String>,
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            background: None,
+            object0: None,
+            object1: None,
+            error: None,
+        }
+    }
+
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("invalid palette line: {}", line))?;
+        let shades = parse_shades(value)?;
+        match key.trim() {
+            "background" => self.background = Some(shades),
+            "object0" => self.object0 = Some(shades),
+            "object1" => self.object1 = Some(shades),
+            key => return Err(format!("unknown palette key {}", key)),
+        }
+        Ok(())
+    }
+
+    fn into_color_scheme(self) -> Result<ColorScheme, String> {
+        if let Some(error) = self.error {
+            return Err(format!("skipping palette {}: {}", self.name, error));
+        }
+        let background = self
+            .background
+            .ok_or_else(|| format!("skipping palette {}: missing background colors", self.name))?;
+        let object0 = self.object0.unwrap_or_else(|| background.clone());
+        let object1 = self.object1.unwrap_or_else(|| background.clone());
+        Ok(ColorScheme::new(&self.name, background, object0, object1))
+    }
+}
+
+// the palettes that parsed and warnings for the lines and palettes that didn't
+fn parse_color_schemes(config: &str) -> (Vec<ColorScheme>, Vec<String>) {
+    let mut sections = vec![];
+    let mut section: Option<Section> = None;
+    let mut warnings = vec![];
+
+    for line in config.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            sections.extend(section.take());
+            section = Some(Section::new(name.trim()));
+            continue;
+        }
+
+        match section.as_mut() {
+            Some(current) if current.error.is_none() => current.error = current.set(line).err(),
+            Some(_) => {}
+            None => warnings.push(format!(
+                "palette colors outside of a [name] section: {}",
+                line
+            )),
+        }
+    }
+    sections.extend(section);
+
+    let mut schemes = vec![];
+    for section in sections {
+        match section.into_color_scheme() {
+            Ok(scheme) => schemes.push(scheme),
+            Err(warning) => warnings.push(warning),
+        }
+    }
+    (schemes, warnings)
+}
+
+fn parse_shades(value: &str) -> Result<Shades, String> {
+    let colors = value
+        .split_whitespace()
+        .map(parse_color)
+        .collect::<Result<Vec<Color>, String>>()?;
+    let colors: [Color; 4] = colors
+        .try_into()
+        .map_err(|_| format!("expected 4 palette colors but got: {}", value.trim()))?;
+    Ok(Shades::new(colors))
+}
+
+fn parse_color(hex: &str) -> Result<Color, String> {
+    let hex = hex.trim_start_matches('#');
+    let rgb = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)
+        .ok_or_else(|| format!("invalid palette color {}", hex))?;
+    Ok(Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color_schemes() {
+        let config = "
+            # comment
+            [first]
+            background = FFFFFF #AAAAAA 555555 000000
+            object1 = 010203 040506 070809 0A0B0C
+
+            [second]
+            background = 000000 000000 000000 000000
+        ";
+
+        let (schemes, warnings) = parse_color_schemes(config);
+
+        assert!(warnings.is_empty());
+        assert_eq!(schemes.len(), 2);
+        assert_eq!(schemes[0].name, "first");
+        assert_eq!(schemes[0].background.color(1), Color::rgb(0xAA, 0xAA, 0xAA));
+        assert_eq!(schemes[0].object0, schemes[0].background);
+        assert_eq!(schemes[0].object1.color(3), Color::rgb(0x0A, 0x0B, 0x0C));
+        assert_eq!(schemes[1].name, "second");
+    }
+
+    #[test]
+    fn test_parse_color_schemes_skips_bad_palettes() {
+        let config = "
+            background = FFFFFF 000000 000000 000000
+            [too_few]
+            background = FFFFFF 000000
+            [invalid]
+            background = FFFFFF 000000 GGGGGG 000000
+            [good]
+            background = 000000 000000 000000 000000
+            [no_background]
+            object0 = 000000 000000 000000 000000
+            [unknown]
+            object2 = 000000 000000 000000 000000
+        ";
+
+        let (schemes, warnings) = parse_color_schemes(config);
+
+        assert_eq!(schemes.len(), 1);
+        assert_eq!(schemes[0].name, "good");
+        assert_eq!(
+            warnings,
+            [
+                "palette colors outside of a [name] section: \
+                 background = FFFFFF 000000 000000 000000",
+                "skipping palette too_few: expected 4 palette colors but got: FFFFFF 000000",
+                "skipping palette invalid: invalid palette color GGGGGG",
+                "skipping palette no_background: missing background colors",
+                "skipping palette unknown: unknown palette key object2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_color_schemes_missing_file() {
+        let schemes = load_color_schemes("aaaaa");
+
+        assert_eq!(schemes, ColorScheme::presets());
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use super::display::Frame;
+use crate::gameboy::Color;
+
+// 4194304 Hz / 70224 cycles per frame, about 59.73 frames a second
+const FRAME_RATE: (u32, u32) = (4194304, 70224);
+
+// records video as uncompressed y4m (yuv 4:4:4, so no color is lost to subsampling).
+// every emulated frame is written exactly once, so the video plays back at the gameboy's
+// own frame rate however fast or slow the host was running
+pub struct Recorder {
+    writer: BufWriter<File>,
+    path: String,
+    width: usize,
+    height: usize,
+    frames: u32,
+}
+
+impl Recorder {
+    pub fn new(path: &str, width: usize, height: usize) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+            path: path.to_string(),
+            width,
+            height,
+            frames: 0,
+        };
+        let header = format!(
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444\n",
+            width, height, FRAME_RATE.0, FRAME_RATE.1
+        );
+        recorder.writer.write_all(header.as_bytes())?;
+        Ok(recorder)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        if (frame.width, frame.height) != (self.width, self.height) {
+            panic!(
+                "recording is {}x{} but the frame is {}x{}",
+                self.width, self.height, frame.width, frame.height
+            );
+        }
+
+        let yuv: Vec<[u8; 3]> = frame.pixels.iter().map(|color| to_yuv(*color)).collect();
+        let mut data = Vec::with_capacity(yuv.len() * 3);
+        for plane in 0..3 {
+            data.extend(yuv.iter().map(|pixel| pixel[plane]));
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&data)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// bt.601 limited range, which is what players assume for y4m
+fn to_yuv(color: Color) -> [u8; 3] {
+    let (r, g, b) = (color.r as i32, color.g as i32, color.b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, u as u8, v as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_yuv() {
+        assert_eq!(to_yuv(Color::WHITE), [235, 128, 128]);
+        assert_eq!(to_yuv(Color::BLACK), [16, 128, 128]);
+        assert_eq!(to_yuv(Color::rgb(0xFF, 0, 0)), [82, 90, 240]);
+    }
+
+    #[test]
+    fn test_write_frames() {
+        let path = std::env::temp_dir().join("garlickboy_test_write_frames.y4m");
+        let path = path.to_str().unwrap();
+        let mut frame = Frame::new(2, 1);
+        frame.set_pixel(1, 0, Color::BLACK);
+
+        let mut recorder = Recorder::new(path, 2, 1).unwrap();
+        recorder.write_frame(&frame).unwrap();
+        recorder.write_frame(&frame).unwrap();
+        assert_eq!(recorder.frames(), 2);
+        recorder.finish().unwrap();
+        let data = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let header = b"YUV4MPEG2 W2 H1 F4194304:70224 Ip A1:1 C444\n";
+        assert_eq!(&data[..header.len()], header);
+        let first = &data[header.len()..header.len() + 12];
+        assert_eq!(first, b"FRAME\n\xEB\x10\x80\x80\x80\x80");
+        assert_eq!(data.len(), header.len() + 2 * 12);
+    }
+}
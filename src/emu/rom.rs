@@ -7,6 +7,7 @@ use std::str::from_utf8;
 
 struct RomHeaderRaw {
     old_licensee_code: u8,
+    new_licensee_code: Vec<u8>,
     destination_code: u8,
     mask_rom_version_number: u8,
     header_checksum: u8,
@@ -21,8 +22,19 @@ struct RomHeaderRaw {
     logo: Vec<u8>,
 }
 
+const CGB_FLAG_BIT: u8 = 1 << 7;
+const SGB_FLAG: u8 = 0x03;
+// sgb functions are only enabled for games using the new licensee code
+const NEW_LICENSEE_CODE: u8 = 0x33;
+const NINTENDO_OLD_LICENSEE_CODE: u8 = 0x01;
+const NINTENDO_NEW_LICENSEE_CODE: &[u8] = b"01";
+
 pub struct RomHeader {
     title: String,
+    cgb_flag: u8,
+    sgb_flag: u8,
+    old_licensee_code: u8,
+    new_licensee_code: Vec<u8>,
     header_checksum: u8,
     global_checksum: u16,
 }
@@ -54,6 +66,33 @@ impl Rom {
             data: data.to_vec(),
         }
     }
+
+    // 0x80 is a cgb enhanced game that also runs on dmg, 0xC0 is cgb only
+    pub fn is_cgb(&self) -> bool {
+        self.header.cgb_flag & CGB_FLAG_BIT != 0
+    }
+
+    pub fn is_sgb(&self) -> bool {
+        self.header.sgb_flag == SGB_FLAG && self.header.old_licensee_code == NEW_LICENSEE_CODE
+    }
+
+    pub fn is_nintendo(&self) -> bool {
+        match self.header.old_licensee_code {
+            NEW_LICENSEE_CODE => self.header.new_licensee_code == NINTENDO_NEW_LICENSEE_CODE,
+            code => code == NINTENDO_OLD_LICENSEE_CODE,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.header.title
+    }
+
+    // older games use all 16 bytes for the title, the last being the cgb flag on newer ones
+    pub fn title_bytes(&self) -> Vec<u8> {
+        let mut title = self.header.title.as_bytes().to_vec();
+        title.push(self.header.cgb_flag);
+        title
+    }
 }
 
 fn load_rom_header_raw(data: Vec<u8>) -> RomHeaderRaw {
@@ -63,6 +102,7 @@ fn load_rom_header_raw(data: Vec<u8>) -> RomHeaderRaw {
         sgb_flag: data[0x0146],
         destination_code: data[0x014A],
         old_licensee_code: data[0x014B],
+        new_licensee_code: data[0x0144..0x0146].to_vec(),
         mask_rom_version_number: data[0x014C],
         header_checksum: data[0x014D],
         global_checksum: data[0x014E..0x0150].to_vec(),
@@ -83,6 +123,10 @@ fn load_rom_header(data: Vec<u8>) -> RomHeader {
 
     RomHeader {
         title,
+        cgb_flag: rom_header_raw.cgb_flag,
+        sgb_flag: rom_header_raw.sgb_flag,
+        old_licensee_code: rom_header_raw.old_licensee_code,
+        new_licensee_code: rom_header_raw.new_licensee_code,
         header_checksum: rom_header_raw.header_checksum,
         global_checksum: ((rom_header_raw.global_checksum[0] as u16) << 8)
             | rom_header_raw.global_checksum[1] as u16,
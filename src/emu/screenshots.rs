@@ -0,0 +1,122 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::display::Frame;
+
+// saves frames as pngs named after the rom, eg screenshots/TETRIS_1697712000123.png
+pub struct Screenshots {
+    directory: PathBuf,
+    title: String,
+    scale: usize,
+}
+
+impl Screenshots {
+    pub fn new(directory: &str, title: &str, scale: usize) -> Self {
+        Self {
+            directory: PathBuf::from(directory),
+            title: file_title(title),
+            scale,
+        }
+    }
+
+    // native resolution and, if a scale was given, a scaled copy alongside it
+    pub fn save(&self, frame: &Frame) -> io::Result<Vec<String>> {
+        let name = self.timestamped_name();
+        let mut paths = vec![self.write(frame, &name)?];
+        if self.scale > 1 {
+            let scaled = frame.scale(self.scale);
+            paths.push(self.write(&scaled, &format!("{}_x{}", name, self.scale))?);
+        }
+        Ok(paths)
+    }
+
+    // frame dumps are numbered instead so they sort in order
+    pub fn save_numbered(&self, frame: &Frame, number: u32) -> io::Result<String> {
+        self.write(frame, &format!("{}_frame{:06}", self.title, number))
+    }
+
+    // recordings go in the same directory and are named the same way
+    pub fn recording_path(&self) -> io::Result<String> {
+        self.path(&self.timestamped_name(), "y4m")
+    }
+
+    pub fn audio_recording_path(&self) -> io::Result<String> {
+        self.path(&self.timestamped_name(), "wav")
+    }
+
+    fn timestamped_name(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is before 1970");
+        format!("{}_{}", self.title, now.as_millis())
+    }
+
+    fn write(&self, frame: &Frame, name: &str) -> io::Result<String> {
+        let path = self.path(name, "png")?;
+        frame.save_png(&path)?;
+        Ok(path)
+    }
+
+    fn path(&self, name: &str, extension: &str) -> io::Result<String> {
+        fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(format!("{}.{}", name, extension));
+        Ok(path.to_str().expect("invalid screenshot path").to_string())
+    }
+}
+
+// titles are padded with zeros and can contain characters that aren't safe in file names
+fn file_title(title: &str) -> String {
+    let title: String = title
+        .trim_end_matches('\0')
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if title.is_empty() {
+        "garlickboy".to_string()
+    } else {
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_title() {
+        assert_eq!(file_title("TETRIS\0\0\0\0\0\0\0\0\0"), "TETRIS");
+        assert_eq!(file_title("POKEMON BLUE"), "POKEMON_BLUE");
+        assert_eq!(file_title("\0\0\0"), "garlickboy");
+    }
+
+    #[test]
+    fn test_save_scaled() {
+        let directory = std::env::temp_dir().join("garlickboy_test_save_scaled");
+        let screenshots = Screenshots::new(directory.to_str().unwrap(), "TEST", 2);
+
+        let paths = screenshots.save(&Frame::new(3, 2)).unwrap();
+        let native = Frame::load_png(&paths[0]);
+        let scaled = Frame::load_png(&paths[1]);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!((native.width, native.height), (3, 2));
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert!(paths[1].ends_with("_x2.png"));
+    }
+
+    #[test]
+    fn test_save_into_a_file_fails() {
+        let file = std::env::temp_dir().join("garlickboy_test_save_into_a_file");
+        fs::write(&file, b"").unwrap();
+        let screenshots = Screenshots::new(file.to_str().unwrap(), "TEST", 1);
+
+        let result = screenshots.save(&Frame::new(3, 2));
+        fs::remove_file(&file).unwrap();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,448 @@
+use super::{
+    audio::{new_mixer, wav::WavRecorder, AudioOutput, EMULATED_RATE},
+    controller::{Controller, Keyboard},
+    debug::{DebugView, DebugWindows},
+    display::{self, Display, HeadlessDisplay, SdlDisplay, VIDEO_HEIGHT, VIDEO_WIDTH},
+    options::Options,
+    palettes::{self, PALETTES_CONFIG},
+    recorder::Recorder,
+    rom::Rom,
+    screenshots::Screenshots,
+};
+use crate::gameboy::{manual_scheme, Buttons, ColorScheme, Gameboy, Mixer, FRAME_DURATION};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    EventPump,
+};
+use std::{io, process, thread, time::Instant};
+
+const NEXT_PALETTE_KEY: Keycode = Keycode::P;
+const SCALE_UP_KEY: Keycode = Keycode::Equals;
+const SCALE_DOWN_KEY: Keycode = Keycode::Minus;
+const NEXT_BLEND_KEY: Keycode = Keycode::F7;
+const NEXT_FILTER_KEY: Keycode = Keycode::F8;
+const FULLSCREEN_KEY: Keycode = Keycode::F11;
+const RECORD_AUDIO_KEY: Keycode = Keycode::F9;
+const RECORD_KEY: Keycode = Keycode::F10;
+const SCREENSHOT_KEY: Keycode = Keycode::F12;
+// mute pulse 1, pulse 2, wave and noise, see change_channel for the modifiers
+const CHANNEL_KEYS: [Keycode; 4] = [Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4];
+const VOLUME_DOWN_KEY: Keycode = Keycode::LeftBracket;
+const VOLUME_UP_KEY: Keycode = Keycode::RightBracket;
+const HIGH_PASS_KEY: Keycode = Keycode::H;
+const DEBUG_VIEW_KEYS: [(Keycode, DebugView); 4] = [
+    (Keycode::F1, DebugView::Tiles),
+    (Keycode::F2, DebugView::Maps),
+    (Keycode::F3, DebugView::Oam),
+    (Keycode::F4, DebugView::Palettes),
+];
+
+pub struct Emu {
+    gameboy: Gameboy,
+    // when the next frame is due, unless audio is the master clock
+    next_frame: Instant,
+    audio: Option<AudioOutput>,
+    display: SdlDisplay,
+    keyboard: Keyboard,
+    event_pump: EventPump,
+    debug_windows: DebugWindows,
+    color_schemes: Vec<ColorScheme>,
+    color_scheme: usize,
+    colorized: bool,
+    screenshots: Screenshots,
+    recorder: Option<Recorder>,
+    wav: Option<WavRecorder>,
+    // whether audio recordings started with F9 have stems
+    stems: bool,
+    mixer: Mixer,
+    // screenshots and recordings are drawn into memory so they don't depend on the window
+    capture: HeadlessDisplay,
+}
+
+impl Emu {
+    pub fn new(options: &Options) -> Self {
+        let sdl = sdl2::init().expect("failed to init sdl2");
+        let display = display::new_sdl_display(
+            &sdl,
+            options.scale,
+            options.filter,
+            options.blend,
+            options.fullscreen,
+        );
+        let event_pump = sdl.event_pump().expect("failed to get event_pump");
+        let debug_windows = DebugWindows::new(&sdl);
+        let keyboard = Keyboard::new();
+        let rom = Rom::new(&options.rom);
+        let color_schemes = palettes::rom_color_schemes(&rom, PALETTES_CONFIG);
+        let colorized = !rom.is_cgb();
+        let screenshots = Screenshots::new(
+            &options.screenshot_dir,
+            rom.title(),
+            options.screenshot_scale,
+        );
+        let recorder = options.record.as_ref().map(|path| {
+            new_recorder(path)
+                .unwrap_or_else(|error| panic!("unable to create {}: {}", path, error))
+        });
+        let mut gameboy = Gameboy::new();
+        gameboy.load_rom(&rom);
+        gameboy.set_color_scheme(color_schemes[0].clone());
+        let audio = options
+            .audio
+            .then(|| AudioOutput::new(&sdl, options.audio_sync));
+        // sampled even with audio off, so a wav recording can start at any time
+        gameboy.set_sample_rate(EMULATED_RATE);
+        let wav = options.wav.as_ref().map(|path| {
+            WavRecorder::new(path, options.stems)
+                .unwrap_or_else(|error| panic!("unable to create {}: {}", path, error))
+        });
+        gameboy.set_stems(options.wav.is_some() && options.stems);
+        let mixer = new_mixer(options);
+        gameboy.set_mixer(mixer);
+
+        Self {
+            next_frame: Instant::now(),
+            audio,
+            display,
+            gameboy,
+            event_pump,
+            debug_windows,
+            keyboard,
+            color_schemes,
+            color_scheme: 0,
+            colorized,
+            screenshots,
+            recorder,
+            wav,
+            stems: options.stems,
+            mixer,
+            capture: HeadlessDisplay::new(),
+        }
+    }
+
+    fn handle_events(&mut self) {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => self.quit(),
+                Event::KeyDown {
+                    keycode: Some(NEXT_PALETTE_KEY),
+                    repeat: false,
+                    ..
+                } => self.next_color_scheme(),
+                Event::KeyDown {
+                    keycode: Some(SCALE_UP_KEY),
+                    ..
+                } => self.display.set_scale(self.display.scale() + 1),
+                Event::KeyDown {
+                    keycode: Some(SCALE_DOWN_KEY),
+                    ..
+                } => self.display.set_scale(self.display.scale() - 1),
+                Event::KeyDown {
+                    keycode: Some(NEXT_BLEND_KEY),
+                    repeat: false,
+                    ..
+                } => self.next_blend(),
+                Event::KeyDown {
+                    keycode: Some(NEXT_FILTER_KEY),
+                    repeat: false,
+                    ..
+                } => self.next_filter(),
+                Event::KeyDown {
+                    keycode: Some(FULLSCREEN_KEY),
+                    repeat: false,
+                    ..
+                } => self.display.toggle_fullscreen(),
+                Event::KeyDown {
+                    keycode: Some(SCREENSHOT_KEY),
+                    repeat: false,
+                    ..
+                } => self.save_screenshot(),
+                Event::KeyDown {
+                    keycode: Some(RECORD_KEY),
+                    repeat: false,
+                    ..
+                } => self.toggle_recording(),
+                Event::KeyDown {
+                    keycode: Some(RECORD_AUDIO_KEY),
+                    repeat: false,
+                    ..
+                } => self.toggle_audio_recording(),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if CHANNEL_KEYS.contains(&keycode) => self.change_channel(keycode, keymod),
+                Event::KeyDown {
+                    keycode: Some(VOLUME_DOWN_KEY),
+                    ..
+                } => self.change_mixer(Mixer::volume_down),
+                Event::KeyDown {
+                    keycode: Some(VOLUME_UP_KEY),
+                    ..
+                } => self.change_mixer(Mixer::volume_up),
+                Event::KeyDown {
+                    keycode: Some(HIGH_PASS_KEY),
+                    repeat: false,
+                    ..
+                } => self.change_mixer(|mixer| mixer.high_pass = !mixer.high_pass),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => self.toggle_debug_view(keycode),
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } if !self.debug_windows.close(window_id) => self.quit(),
+                _ => {}
+            }
+        }
+    }
+
+    fn next_color_scheme(&mut self) {
+        self.color_scheme = (self.color_scheme + 1) % self.color_schemes.len();
+        let scheme = self.color_schemes[self.color_scheme].clone();
+        println!("palette: {}", scheme.name);
+        self.gameboy.set_color_scheme(scheme);
+    }
+
+    fn next_filter(&mut self) {
+        let filter = self.display.filter().next();
+        println!("filter: {}", filter.name());
+        self.display.set_filter(filter);
+    }
+
+    fn next_blend(&mut self) {
+        let blend = self.display.blend().next();
+        println!("frame blending: {}", blend.name());
+        self.display.set_blend(blend);
+    }
+
+    // on its own a channel key mutes the channel, with shift it solos it and with ctrl or alt
+    // it turns the channel down or up
+    fn change_channel(&mut self, keycode: Keycode, keymod: Mod) {
+        let channel = CHANNEL_KEYS.iter().position(|key| *key == keycode).unwrap();
+        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+            self.change_mixer(|mixer| mixer.toggle_solo(channel));
+        } else if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+            self.change_mixer(|mixer| mixer.gain_down(channel));
+        } else if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+            self.change_mixer(|mixer| mixer.gain_up(channel));
+        } else {
+            self.change_mixer(|mixer| mixer.toggle_mute(channel));
+        }
+    }
+
+    fn change_mixer(&mut self, change: impl FnOnce(&mut Mixer)) {
+        change(&mut self.mixer);
+        println!("mixer: {}", self.mixer);
+        self.gameboy.set_mixer(self.mixer);
+    }
+
+    fn quit(&mut self) -> ! {
+        self.stop_recording();
+        self.stop_audio_recording();
+        process::exit(0)
+    }
+
+    fn save_screenshot(&mut self) {
+        self.gameboy.render_display(&mut self.capture);
+        match self.screenshots.save(self.capture.frame()) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("saved screenshot {}", path);
+                }
+            }
+            Err(error) => eprintln!("unable to save screenshot: {}", error),
+        }
+    }
+
+    // a recording that can't be written is reported and the game carries on without it
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_some() {
+            self.stop_recording();
+            return;
+        }
+        match self
+            .screenshots
+            .recording_path()
+            .and_then(|path| new_recorder(&path))
+        {
+            Ok(recorder) => {
+                println!("recording {}", recorder.path());
+                self.recorder = Some(recorder);
+            }
+            Err(error) => eprintln!("unable to start recording: {}", error),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let path = recorder.path().to_string();
+            let frames = recorder.frames();
+            match recorder.finish() {
+                Ok(()) => println!("saved recording {} ({} frames)", path, frames),
+                Err(error) => eprintln!("failed to write {}: {}", path, error),
+            }
+        }
+    }
+
+    fn toggle_audio_recording(&mut self) {
+        if self.wav.is_some() {
+            self.stop_audio_recording();
+            return;
+        }
+        match self
+            .screenshots
+            .audio_recording_path()
+            .and_then(|path| WavRecorder::new(&path, self.stems))
+        {
+            Ok(wav) => {
+                println!("recording audio {}", wav.path());
+                self.gameboy.set_stems(wav.has_stems());
+                self.wav = Some(wav);
+            }
+            Err(error) => eprintln!("unable to start audio recording: {}", error),
+        }
+    }
+
+    fn stop_audio_recording(&mut self) {
+        if let Some(wav) = self.wav.take() {
+            let path = wav.path().to_string();
+            let seconds = wav.seconds();
+            self.gameboy.set_stems(false);
+            match wav.finish() {
+                Ok(()) => println!("saved audio recording {} ({:.1} seconds)", path, seconds),
+                Err(error) => eprintln!("failed to write {}: {}", path, error),
+            }
+        }
+    }
+
+    // called once per emulated frame rather than per window refresh, so recordings are
+    // frame exact however the host paces the window
+    fn record_frame(&mut self) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            self.gameboy.render_display(&mut self.capture);
+            if let Err(error) = recorder.write_frame(self.capture.frame()) {
+                eprintln!(
+                    "failed to write {}, recording stopped: {}",
+                    recorder.path(),
+                    error
+                );
+                self.recorder = None;
+            }
+        }
+    }
+
+    fn toggle_debug_view(&mut self, keycode: Keycode) {
+        if let Some((_, view)) = DEBUG_VIEW_KEYS.iter().find(|(key, _)| *key == keycode) {
+            let state = self.gameboy.ppu_state();
+            self.debug_windows.toggle(*view, &state);
+        }
+    }
+
+    fn input(&mut self) {
+        self.keyboard.read(&self.event_pump);
+        let buttons = self.keyboard.buttons();
+        self.gameboy.update_joypad(&buttons);
+        if self.colorized && self.gameboy.is_booting() {
+            self.pick_manual_color_scheme(&buttons);
+        }
+    }
+
+    // holding a direction and optionally a or b during the boot logo picks one of the
+    // cgb's manual palettes instead
+    fn pick_manual_color_scheme(&mut self, buttons: &Buttons) {
+        if let Some(scheme) = manual_scheme(buttons) {
+            if scheme != self.color_schemes[0] {
+                self.color_schemes[0] = scheme.clone();
+                self.color_scheme = 0;
+                self.gameboy.set_color_scheme(scheme);
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        self.gameboy.render_display(&mut self.display);
+        self.display.present();
+        self.record_frame();
+        if self.debug_windows.is_open() {
+            let state = self.gameboy.ppu_state();
+            self.debug_windows.update(&state);
+        }
+        self.output_audio();
+        self.wait_for_next_frame();
+    }
+
+    fn output_audio(&mut self) {
+        let samples = self.gameboy.take_samples();
+        let stems = self.gameboy.take_stems();
+        if let Some(wav) = self.wav.as_mut() {
+            // what was recorded so far is kept, finishing gives it a proper header
+            if let Err(error) = wav.write(&samples, &stems) {
+                eprintln!(
+                    "failed to write {}, audio recording stopped: {}",
+                    wav.path(),
+                    error
+                );
+                self.stop_audio_recording();
+            } else if wav.is_full() {
+                println!("{} is as long as a wav can be", wav.path());
+                self.stop_audio_recording();
+            }
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            audio.queue(&samples);
+        }
+    }
+
+    // frames are paced at the gameboy's own rate by the clock, with rate control keeping the
+    // audio in step, or by the audio device when it's the master clock
+    fn wait_for_next_frame(&mut self) {
+        match self.audio.as_ref() {
+            Some(audio) if audio.is_master() => audio.wait(),
+            _ => {
+                let now = Instant::now();
+                self.next_frame += FRAME_DURATION;
+                // after a stall carry on from now rather than rushing to catch up
+                if self.next_frame + FRAME_DURATION < now {
+                    self.next_frame = now;
+                }
+                thread::sleep(self.next_frame.saturating_duration_since(now));
+            }
+        }
+    }
+
+    fn run_cycles(&mut self) {
+        for cycle in self.gameboy.cycles() {
+            if self.gameboy.is_new_frame() {
+                self.handle_events();
+                self.input();
+            }
+
+            self.gameboy.execute(cycle);
+
+            if self.gameboy.is_new_frame() {
+                self.present();
+            }
+        }
+    }
+
+    pub fn run(&mut self) {
+        loop {
+            self.run_cycles();
+        }
+    }
+}
+
+fn new_recorder(path: &str) -> io::Result<Recorder> {
+    Recorder::new(path, VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize)
+}
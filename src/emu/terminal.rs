@@ -0,0 +1,31 @@
+use std::{thread, time::Instant};
+
+use super::{
+    controller::{Controller, TerminalController},
+    display,
+    options::Options,
+    palettes::{self, PALETTES_CONFIG},
+    rom::Rom,
+};
+use crate::gameboy::{Gameboy, FRAME_DURATION};
+
+// plays a rom in the terminal, for machines without a window system
+pub fn run_terminal(options: &Options) {
+    let rom = Rom::new(&options.rom);
+    let color_schemes = palettes::rom_color_schemes(&rom, PALETTES_CONFIG);
+    let mut gameboy = Gameboy::new();
+    gameboy.load_rom(&rom);
+    gameboy.set_color_scheme(color_schemes[0].clone());
+
+    let mut display = display::new_terminal_display(options.blend);
+    let mut controller = TerminalController::new();
+    let mut next_frame = Instant::now();
+    while !controller.quit_requested() {
+        let buttons = controller.buttons();
+        gameboy.update_joypad(&buttons);
+        gameboy.run_frames(1, display.as_mut());
+
+        next_frame += FRAME_DURATION;
+        thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+}
@@ -0,0 +1,157 @@
+const MAX_VOLUME: u8 = 15;
+
+// NRx2, steps the volume up or down every `period` 64 Hz ticks
+pub struct Envelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+    volume: u8,
+    timer: u8,
+    // stops once the volume hits 0 or 15, until the next trigger
+    running: bool,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self {
+            initial_volume: 0,
+            increase: false,
+            period: 0,
+            volume: 0,
+            timer: 0,
+            running: false,
+        }
+    }
+
+    // writing while the channel is playing nudges the volume, "zombie mode". games like
+    // prehistorik man use it to change the volume without retriggering
+    pub fn write(&mut self, byte: u8, playing: bool) {
+        let (was_increase, old_period) = (self.increase, self.period);
+        self.initial_volume = byte >> 4;
+        self.increase = byte & 0b1000 != 0;
+        self.period = byte & 0b111;
+        if !playing {
+            return;
+        }
+        // the volume is 4 bits and wraps around
+        if old_period == 0 && self.running {
+            self.volume = (self.volume + 1) & 0x0F;
+        } else if !was_increase {
+            self.volume = (self.volume + 2) & 0x0F;
+        }
+        if was_increase != self.increase {
+            self.volume = 16u8.wrapping_sub(self.volume) & 0x0F;
+        }
+    }
+
+    // the channel's dac is off when the top 5 bits are all clear
+    pub fn is_dac_enabled(&self) -> bool {
+        self.initial_volume != 0 || self.increase
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.period;
+        self.running = true;
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 || !self.running {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.period;
+        if self.increase && self.volume < MAX_VOLUME {
+            self.volume += 1;
+        } else if !self.increase && self.volume > 0 {
+            self.volume -= 1;
+        } else {
+            self.running = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade_out() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x22, false);
+        envelope.trigger();
+
+        let volumes: Vec<u8> = (0..6)
+            .map(|_| {
+                envelope.clock();
+                envelope.volume()
+            })
+            .collect();
+        assert_eq!(volumes, [2, 1, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fade_in_stops_at_max() {
+        let mut envelope = Envelope::new();
+        envelope.write(0xE9, false);
+        envelope.trigger();
+
+        (0..5).for_each(|_| envelope.clock());
+        assert_eq!(envelope.volume(), MAX_VOLUME);
+    }
+
+    #[test]
+    fn test_dac() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x07, false);
+        assert!(!envelope.is_dac_enabled());
+        envelope.write(0x08, false);
+        assert!(envelope.is_dac_enabled());
+    }
+
+    #[test]
+    fn test_zombie_mode() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x50, false);
+        envelope.trigger();
+
+        // a running envelope with no period goes up by 1
+        envelope.write(0x50, true);
+        assert_eq!(envelope.volume(), 6);
+        // and flipping the direction mirrors it
+        envelope.write(0x58, true);
+        assert_eq!(envelope.volume(), 9);
+    }
+
+    #[test]
+    fn test_zombie_mode_after_stopping() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x11, false);
+        envelope.trigger();
+        (0..2).for_each(|_| envelope.clock());
+
+        // a decreasing envelope goes up by 2
+        envelope.write(0x11, true);
+        assert_eq!(envelope.volume(), 2);
+    }
+
+    #[test]
+    fn test_zombie_mode_wraps_from_max() {
+        // 15 + 2 wraps to 1 which mirrors to 15, 14 + 2 wraps to 0 which mirrors to 0
+        for (initial, expected) in [(0xF3, 15), (0xE3, 0)] {
+            let mut envelope = Envelope::new();
+            envelope.write(initial, false);
+            envelope.trigger();
+
+            envelope.write(initial | 0b1000, true);
+            assert_eq!(envelope.volume(), expected);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// counts down at 256 Hz and silences the channel when it runs out, if enabled
+#[derive(Clone, Copy)]
+pub struct Length {
+    max: u16,
+    counter: u16,
+    pub enabled: bool,
+}
+
+impl Length {
+    pub fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    pub fn load(&mut self, length: u8) {
+        self.counter = self.max - length as u16;
+    }
+
+    // NRx4's enable bit. `extra_clock` is set when the frame sequencer's next step won't clock
+    // lengths, enabling the counter then clocks it once straight away. true if that ran it out
+    pub fn write_enable(&mut self, enabled: bool, extra_clock: bool) -> bool {
+        let was_enabled = self.enabled;
+        self.enabled = enabled;
+        !was_enabled && extra_clock && self.clock()
+    }
+
+    // a channel triggered with nothing left to count plays the full length, less the extra
+    // clock it would have had when enabled as above
+    pub fn trigger(&mut self, extra_clock: bool) {
+        if self.counter == 0 {
+            self.counter = self.max;
+            if self.enabled && extra_clock {
+                self.counter -= 1;
+            }
+        }
+    }
+
+    // powering off the dmg clears the enable bit but leaves the counter
+    pub fn powered_off(self) -> Self {
+        Self {
+            enabled: false,
+            ..self
+        }
+    }
+
+    // true if the counter just ran out
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_out() {
+        let mut length = Length::new(64);
+        length.load(62);
+        length.enabled = true;
+
+        assert!(!length.clock());
+        assert!(length.clock());
+        assert!(!length.clock());
+    }
+
+    #[test]
+    fn test_trigger_reloads_empty_counter() {
+        let mut length = Length::new(256);
+        length.trigger(false);
+        length.enabled = true;
+
+        assert!((0..255).all(|_| !length.clock()));
+        assert!(length.clock());
+    }
+
+    #[test]
+    fn test_extra_clock_when_enabled() {
+        let mut length = Length::new(64);
+        length.load(62);
+
+        assert!(!length.write_enable(true, true));
+        assert_eq!(length.counter, 1);
+        // already enabled, so no extra clock
+        assert!(!length.write_enable(true, true));
+        length.write_enable(false, true);
+        assert!(length.write_enable(true, true));
+    }
+
+    #[test]
+    fn test_trigger_after_extra_clock() {
+        let mut length = Length::new(64);
+        length.write_enable(true, true);
+        length.trigger(true);
+
+        assert_eq!(length.counter, 63);
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+
+use super::{Sample, Stems, CHANNELS};
+
+const CHANNEL_NAMES: [&str; CHANNELS] = ["pulse1", "pulse2", "wave", "noise"];
+const GAIN_STEP: f32 = 0.25;
+const MAX_GAIN: f32 = 2.0;
+const VOLUME_STEP: f32 = 0.1;
+const MAX_VOLUME: f32 = 1.0;
+// how much of its charge the output capacitor keeps each t-cycle
+const DMG_CHARGE: f32 = 0.999958;
+const CGB_CHARGE: f32 = 0.998943;
+const T_CYCLES_PER_SECOND: f32 = 4194304.0;
+
+// how the channels are put together into the output. new passes them through as the
+// hardware mixes them, the rest is for listening to parts of a song or hunting a glitch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mixer {
+    pub muted: [bool; CHANNELS],
+    // with any channel soloed only soloed channels are heard, muted or not
+    pub soloed: [bool; CHANNELS],
+    pub gains: [f32; CHANNELS],
+    pub volume: f32,
+    // the dmg's dc blocking capacitor, see HighPass
+    pub high_pass: bool,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Self {
+            muted: [false; CHANNELS],
+            soloed: [false; CHANNELS],
+            gains: [1.0; CHANNELS],
+            volume: 1.0,
+            high_pass: false,
+        }
+    }
+
+    pub fn is_audible(&self, channel: usize) -> bool {
+        if self.soloed.contains(&true) {
+            self.soloed[channel]
+        } else {
+            !self.muted[channel]
+        }
+    }
+
+    pub fn toggle_mute(&mut self, channel: usize) {
+        self.muted[channel] = !self.muted[channel];
+    }
+
+    pub fn toggle_solo(&mut self, channel: usize) {
+        self.soloed[channel] = !self.soloed[channel];
+    }
+
+    pub fn gain_up(&mut self, channel: usize) {
+        self.gains[channel] = step(self.gains[channel], GAIN_STEP, MAX_GAIN);
+    }
+
+    pub fn gain_down(&mut self, channel: usize) {
+        self.gains[channel] = step(self.gains[channel], -GAIN_STEP, MAX_GAIN);
+    }
+
+    pub fn volume_up(&mut self) {
+        self.volume = step(self.volume, VOLUME_STEP, MAX_VOLUME);
+    }
+
+    pub fn volume_down(&mut self) {
+        self.volume = step(self.volume, -VOLUME_STEP, MAX_VOLUME);
+    }
+
+    pub fn mix(&self, stems: &Stems) -> Sample {
+        let mut mixed = Sample::default();
+        for (channel, stem) in stems.iter().enumerate() {
+            if !self.is_audible(channel) {
+                continue;
+            }
+            let gain = self.gains[channel] * self.volume;
+            mixed.left += stem.left * gain;
+            mixed.right += stem.right * gain;
+        }
+        mixed
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// eg "pulse1 100%, pulse2 muted, wave 50% solo, noise 100%, volume 80%, high pass on"
+impl fmt::Display for Mixer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (channel, name) in CHANNEL_NAMES.iter().enumerate() {
+            write!(f, "{} {}", name, percent(self.gains[channel]))?;
+            if self.muted[channel] {
+                write!(f, " muted")?;
+            }
+            if self.soloed[channel] {
+                write!(f, " solo")?;
+            }
+            write!(f, ", ")?;
+        }
+        let high_pass = if self.high_pass { "on" } else { "off" };
+        write!(
+            f,
+            "volume {}, high pass {}",
+            percent(self.volume),
+            high_pass
+        )
+    }
+}
+
+// rounded to the step so repeated steps don't drift
+fn step(value: f32, step: f32, max: f32) -> f32 {
+    let stepped = ((value + step) / step.abs()).round() * step.abs();
+    stepped.clamp(0.0, max)
+}
+
+fn percent(value: f32) -> String {
+    format!("{}%", (value * 100.0).round())
+}
+
+// the dmg's output goes through a capacitor that blocks dc, so a channel left at a steady
+// level fades to silence rather than holding the speaker off center. the cgb's charges
+// faster, cutting a little more of the bass
+pub struct HighPass {
+    // how much charge is kept from one sample to the next
+    charge: f32,
+    capacitor: Sample,
+}
+
+impl HighPass {
+    pub fn new(sample_rate: u32, cgb: bool) -> Self {
+        let charge = if cgb { CGB_CHARGE } else { DMG_CHARGE };
+        Self {
+            charge: charge.powf(T_CYCLES_PER_SECOND / sample_rate as f32),
+            capacitor: Sample::default(),
+        }
+    }
+
+    pub fn apply(&mut self, sample: Sample) -> Sample {
+        let output = Sample {
+            left: sample.left - self.capacitor.left,
+            right: sample.right - self.capacitor.right,
+        };
+        self.capacitor = Sample {
+            left: sample.left - output.left * self.charge,
+            right: sample.right - output.right * self.charge,
+        };
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEMS: Stems = [
+        Sample {
+            left: 0.25,
+            right: 0.0,
+        },
+        Sample {
+            left: 0.0,
+            right: 0.25,
+        },
+        Sample {
+            left: 0.125,
+            right: 0.125,
+        },
+        Sample {
+            left: -0.25,
+            right: -0.25,
+        },
+    ];
+
+    fn mixed(mixer: &Mixer) -> (f32, f32) {
+        let sample = mixer.mix(&STEMS);
+        (sample.left, sample.right)
+    }
+
+    #[test]
+    fn test_mute_and_solo() {
+        let mut mixer = Mixer::new();
+        assert_eq!(mixed(&mixer), (0.125, 0.125));
+
+        mixer.toggle_mute(3);
+        assert_eq!(mixed(&mixer), (0.375, 0.375));
+        // a solo wins over a mute
+        mixer.toggle_solo(3);
+        mixer.toggle_solo(0);
+        assert_eq!(mixed(&mixer), (0.0, -0.25));
+        mixer.toggle_solo(3);
+        assert_eq!(mixed(&mixer), (0.25, 0.0));
+    }
+
+    #[test]
+    fn test_gain_and_volume() {
+        let mut mixer = Mixer::new();
+        mixer.gain_up(0);
+        mixer.gain_down(1);
+        mixer.gain_down(2);
+        (0..6).for_each(|_| mixer.gain_down(2));
+        assert_eq!(mixer.gains, [1.25, 0.75, 0.0, 1.0]);
+        assert_eq!(mixed(&mixer), (0.0625, -0.0625));
+
+        (0..5).for_each(|_| mixer.volume_down());
+        mixer.volume_up();
+        assert_eq!(mixer.volume, 0.6);
+        (0..10).for_each(|_| mixer.volume_up());
+        assert_eq!(mixer.volume, MAX_VOLUME);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut mixer = Mixer::new();
+        mixer.toggle_mute(1);
+        mixer.toggle_solo(2);
+        mixer.gain_down(2);
+        mixer.high_pass = true;
+
+        assert_eq!(
+            mixer.to_string(),
+            "pulse1 100%, pulse2 100% muted, wave 75% solo, noise 100%, volume 100%, high pass on"
+        );
+    }
+
+    #[test]
+    fn test_high_pass_blocks_dc() {
+        let mut high_pass = HighPass::new(48000, false);
+        let level = Sample {
+            left: 0.5,
+            right: -0.5,
+        };
+
+        assert_eq!(high_pass.apply(level), level);
+        let settled = (0..48000).map(|_| high_pass.apply(level)).last().unwrap();
+        assert!(settled.left.abs() < 0.001 && settled.right.abs() < 0.001);
+    }
+}
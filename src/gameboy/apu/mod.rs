@@ -0,0 +1,579 @@
+mod envelope;
+mod length;
+mod mixer;
+mod noise;
+mod pulse;
+mod sweep;
+mod wave;
+
+use self::{length::Length, mixer::HighPass, noise::Noise, pulse::Pulse, wave::Wave};
+
+pub use self::mixer::Mixer;
+
+const APU_BEGIN: u16 = 0xFF10;
+const APU_END: u16 = 0xFF3F;
+const NR50_ADDRESS: u16 = 0xFF24;
+const NR51_ADDRESS: u16 = 0xFF25;
+const NR52_ADDRESS: u16 = 0xFF26;
+const NR31_INDEX: u16 = 0x0B;
+const WAVE_RAM_BEGIN: u16 = 0xFF30;
+const POWER_BIT: u8 = 1 << 7;
+// each channel has 5 registers from NRx0
+const CHANNEL_REGISTERS: u16 = 5;
+const CHANNELS: usize = 4;
+// the apu runs at the normal speed m-cycle rate even in double speed
+const CYCLES_PER_SECOND: u32 = 1048576;
+
+// bits that always read back as 1 for NR10 to 0xFF2F, write only and unused bits included
+#[rustfmt::skip]
+const READ_MASKS: [u8; 0x20] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10 - NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20 - NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30 - NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40 - NR44
+    0x00, 0x00, 0x70, 0xFF, 0xFF, // NR50 - NR52
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+// a stereo sample, each side between -1 and 1
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sample {
+    pub left: f32,
+    pub right: f32,
+}
+
+// a sample of each channel on its own, pulse 1, pulse 2, wave and noise, panned and at the
+// volume the hardware gives them. the mixer's high pass is run over each of them too, so
+// they add up to the mix as long as the channels and volume are left as they were
+pub type Stems = [Sample; CHANNELS];
+
+// the four sound channels, panned by NR50 and NR51, put together by the mixer and sampled
+// down to whatever rate the frontend asks for
+pub struct Apu {
+    powered: bool,
+    // last values written to NR10 - 0xFF2F, for reading back
+    registers: [u8; 0x20],
+    pulse1: Pulse,
+    pulse2: Pulse,
+    wave: Wave,
+    noise: Noise,
+    // the step the frame sequencer runs next
+    frame_sequencer_step: u8,
+    cgb: bool,
+    sample_rate: Option<u32>,
+    // adds sample_rate every cycle, a sample is due each time it passes CYCLES_PER_SECOND
+    sample_clock: u32,
+    // every cycle since the last sample is averaged into it
+    sample_sums: Stems,
+    sample_cycles: u32,
+    samples: Vec<Sample>,
+    keep_stems: bool,
+    stems: Vec<Stems>,
+    mixer: Mixer,
+    // only while there's a sample rate, it depends on it
+    high_pass: Option<HighPass>,
+    // the same filter for each stem, it's linear so they still add up to the filtered mix
+    stem_high_passes: Option<[HighPass; CHANNELS]>,
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            powered: false,
+            registers: [0; 0x20],
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            frame_sequencer_step: 0,
+            cgb: false,
+            sample_rate: None,
+            sample_clock: 0,
+            sample_sums: Stems::default(),
+            sample_cycles: 0,
+            samples: vec![],
+            keep_stems: false,
+            stems: vec![],
+            mixer: Mixer::new(),
+            high_pass: None,
+            stem_high_passes: None,
+        }
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        self.reset_high_pass();
+    }
+
+    pub fn handles(&self, address: u16) -> bool {
+        (APU_BEGIN..=APU_END).contains(&address)
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            WAVE_RAM_BEGIN..=APU_END => self.wave.ram[(address - WAVE_RAM_BEGIN) as usize],
+            NR52_ADDRESS => {
+                let power = if self.powered { POWER_BIT } else { 0 };
+                let active = [
+                    self.pulse1.enabled,
+                    self.pulse2.enabled,
+                    self.wave.enabled,
+                    self.noise.enabled,
+                ]
+                .iter()
+                .enumerate()
+                .fold(0, |bits, (channel, enabled)| {
+                    bits | ((*enabled as u8) << channel)
+                });
+                power | active | READ_MASKS[(address - APU_BEGIN) as usize]
+            }
+            _ => {
+                let index = (address - APU_BEGIN) as usize;
+                self.registers[index] | READ_MASKS[index]
+            }
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, byte: u8) {
+        let index = address - APU_BEGIN;
+        match address {
+            WAVE_RAM_BEGIN..=APU_END => {
+                self.wave.ram[(address - WAVE_RAM_BEGIN) as usize] = byte;
+            }
+            NR52_ADDRESS => self.write_power(byte & POWER_BIT != 0),
+            NR50_ADDRESS | NR51_ADDRESS if self.powered => self.registers[index as usize] = byte,
+            _ if self.powered => {
+                self.registers[index as usize] = byte;
+                self.write_channel(index, byte);
+            }
+            // everything else is read only while powered off, except that the dmg can still
+            // load the length counters. the pulse duty bits are left alone
+            NR50_ADDRESS | NR51_ADDRESS => {}
+            _ if !self.cgb && index % CHANNEL_REGISTERS == 1 => {
+                let length = if index == NR31_INDEX {
+                    byte
+                } else {
+                    byte & 0b0011_1111
+                };
+                self.write_channel(index, length);
+            }
+            _ => {}
+        }
+    }
+
+    fn write_channel(&mut self, index: u16, byte: u8) {
+        let register = index % CHANNEL_REGISTERS;
+        // the next step not clocking lengths changes how NRx4 writes load them
+        let extra_length_clock = self.frame_sequencer_step & 1 == 1;
+        match index / CHANNEL_REGISTERS {
+            0 => self.pulse1.write(register, byte, extra_length_clock),
+            1 => self.pulse2.write(register, byte, extra_length_clock),
+            2 => self.wave.write(register, byte, extra_length_clock),
+            3 => self.noise.write(register, byte, extra_length_clock),
+            _ => {}
+        }
+    }
+
+    // 0 disables sample output
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = (sample_rate > 0).then_some(sample_rate);
+        self.sample_clock = 0;
+        self.sample_sums = Stems::default();
+        self.sample_cycles = 0;
+        self.samples.clear();
+        self.stems.clear();
+        self.reset_high_pass();
+    }
+
+    pub fn take_samples(&mut self) -> Vec<Sample> {
+        std::mem::take(&mut self.samples)
+    }
+
+    // keeps each channel's samples as well as the mix, at the same rate
+    pub fn set_stems(&mut self, keep_stems: bool) {
+        self.keep_stems = keep_stems;
+        self.stems.clear();
+    }
+
+    pub fn take_stems(&mut self) -> Vec<Stems> {
+        std::mem::take(&mut self.stems)
+    }
+
+    // the channels and volume only change the mix, stems only get the high pass
+    pub fn set_mixer(&mut self, mixer: Mixer) {
+        self.mixer = mixer;
+    }
+
+    fn reset_high_pass(&mut self) {
+        let high_pass = |sample_rate| HighPass::new(sample_rate, self.cgb);
+        self.high_pass = self.sample_rate.map(high_pass);
+        self.stem_high_passes = self
+            .sample_rate
+            .map(|sample_rate| [(); CHANNELS].map(|_| high_pass(sample_rate)));
+    }
+
+    // one normal speed m-cycle. the frame sequencer steps at 512 Hz on each falling edge of
+    // DIV bit 4 passed in by the timer, so writing to DIV shifts it
+    pub fn update(&mut self, div_apu_events: u8) {
+        if self.powered {
+            (0..div_apu_events).for_each(|_| self.step_frame_sequencer());
+            self.pulse1.step();
+            self.pulse2.step();
+            self.wave.step();
+            self.noise.step();
+        }
+        self.sample();
+    }
+
+    // lengths are clocked on even steps, the sweep on 2 and 6 and envelopes on 7
+    fn step_frame_sequencer(&mut self) {
+        let step = self.frame_sequencer_step;
+        if step & 1 == 0 {
+            self.pulse1.clock_length();
+            self.pulse2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if step == 2 || step == 6 {
+            self.pulse1.clock_sweep();
+        }
+        if step == 7 {
+            self.pulse1.clock_envelope();
+            self.pulse2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+        self.frame_sequencer_step = (step + 1) % 8;
+    }
+
+    // powering off clears every register and stops the channels. wave ram is kept, and so
+    // are the length counters on the dmg
+    fn write_power(&mut self, powered: bool) {
+        if self.powered && !powered {
+            let ram = self.wave.ram;
+            let lengths = [
+                self.pulse1.length,
+                self.pulse2.length,
+                self.wave.length,
+                self.noise.length,
+            ];
+            self.registers = [0; 0x20];
+            self.pulse1 = Pulse::new(true);
+            self.pulse2 = Pulse::new(false);
+            self.wave = Wave::new();
+            self.wave.ram = ram;
+            self.noise = Noise::new();
+            if !self.cgb {
+                let [pulse1, pulse2, wave, noise] = lengths.map(Length::powered_off);
+                self.pulse1.length = pulse1;
+                self.pulse2.length = pulse2;
+                self.wave.length = wave;
+                self.noise.length = noise;
+            }
+        }
+        if !self.powered && powered {
+            self.frame_sequencer_step = 0;
+        }
+        self.powered = powered;
+    }
+
+    fn sample(&mut self) {
+        let Some(sample_rate) = self.sample_rate else {
+            return;
+        };
+        let channels = self.channel_samples();
+        for (sum, channel) in self.sample_sums.iter_mut().zip(channels) {
+            sum.left += channel.left;
+            sum.right += channel.right;
+        }
+        self.sample_cycles += 1;
+
+        self.sample_clock += sample_rate;
+        if self.sample_clock >= CYCLES_PER_SECOND {
+            self.sample_clock -= CYCLES_PER_SECOND;
+            let cycles = self.sample_cycles as f32;
+            let mut stems = self.sample_sums.map(|sum| Sample {
+                left: sum.left / cycles,
+                right: sum.right / cycles,
+            });
+            let mut mixed = self.mixer.mix(&stems);
+            if let (Some(high_pass), Some(stem_high_passes)) =
+                (self.high_pass.as_mut(), self.stem_high_passes.as_mut())
+            {
+                // kept charging while off so turning it on doesn't thump
+                let filtered = high_pass.apply(mixed);
+                let mut filtered_stems = stems;
+                for (stem, high_pass) in filtered_stems.iter_mut().zip(stem_high_passes) {
+                    *stem = high_pass.apply(*stem);
+                }
+                if self.mixer.high_pass {
+                    mixed = filtered;
+                    stems = filtered_stems;
+                }
+            }
+            self.samples.push(mixed);
+            if self.keep_stems {
+                self.stems.push(stems);
+            }
+            self.sample_sums = Stems::default();
+            self.sample_cycles = 0;
+        }
+    }
+
+    // each channel's dac turns its 0 to 15 output into -1 to 1, silent when the dac is off.
+    // NR51 picks which sides each channel goes to and NR50 sets the volume of each side,
+    // and each gets a quarter of the range so the mix stays within -1 to 1
+    fn channel_samples(&self) -> Stems {
+        let outputs = [
+            (self.pulse1.is_dac_enabled(), self.pulse1.output()),
+            (self.pulse2.is_dac_enabled(), self.pulse2.output()),
+            (self.wave.is_dac_enabled(), self.wave.output()),
+            (self.noise.is_dac_enabled(), self.noise.output()),
+        ];
+        let panning = self.registers[(NR51_ADDRESS - APU_BEGIN) as usize];
+        let volume = self.registers[(NR50_ADDRESS - APU_BEGIN) as usize];
+
+        let left_volume = (((volume >> 4) & 0b111) + 1) as f32 / 8.0;
+        let right_volume = ((volume & 0b111) + 1) as f32 / 8.0;
+
+        let mut samples = Stems::default();
+        for (channel, (dac_enabled, output)) in outputs.into_iter().enumerate() {
+            if !dac_enabled {
+                continue;
+            }
+            let analog = (output as f32 / 7.5 - 1.0) / CHANNELS as f32;
+            if panning & (1 << (channel + CHANNELS)) != 0 {
+                samples[channel].left = analog * left_volume;
+            }
+            if panning & (1 << channel) != 0 {
+                samples[channel].right = analog * right_volume;
+            }
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NR11_ADDRESS: u16 = 0xFF11;
+    const NR12_ADDRESS: u16 = 0xFF12;
+    const NR14_ADDRESS: u16 = 0xFF14;
+    const NR22_ADDRESS: u16 = 0xFF17;
+    const NR24_ADDRESS: u16 = 0xFF19;
+
+    fn powered_apu() -> Apu {
+        let mut apu = Apu::new();
+        apu.write_register(NR52_ADDRESS, POWER_BIT);
+        apu
+    }
+
+    #[test]
+    fn test_read_masks() {
+        let mut apu = powered_apu();
+        apu.write_register(0xFF11, 0b10_110011);
+        apu.write_register(0xFF13, 0x12);
+
+        assert_eq!(apu.read_register(0xFF11), 0b10_111111);
+        assert_eq!(apu.read_register(0xFF13), 0xFF);
+        assert_eq!(apu.read_register(0xFF15), 0xFF);
+        assert_eq!(apu.read_register(0xFF2F), 0xFF);
+        assert_eq!(apu.read_register(NR52_ADDRESS), 0xF0);
+    }
+
+    #[test]
+    fn test_channel_status() {
+        let mut apu = powered_apu();
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+
+        assert_eq!(apu.read_register(NR52_ADDRESS), 0xF1);
+    }
+
+    #[test]
+    fn test_power_off_clears_registers() {
+        let mut apu = powered_apu();
+        apu.write_register(NR50_ADDRESS, 0x77);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+        apu.write_register(WAVE_RAM_BEGIN, 0x12);
+
+        apu.write_register(NR52_ADDRESS, 0);
+        assert_eq!(apu.read_register(NR50_ADDRESS), 0);
+        assert_eq!(apu.read_register(NR12_ADDRESS), 0);
+        assert_eq!(apu.read_register(NR52_ADDRESS), 0x70);
+        assert_eq!(apu.read_register(WAVE_RAM_BEGIN), 0x12);
+
+        // and ignores writes until it's powered on again
+        apu.write_register(NR50_ADDRESS, 0x77);
+        assert_eq!(apu.read_register(NR50_ADDRESS), 0);
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let mut apu = powered_apu();
+        assert!((0..1000).all(|_| {
+            apu.update(0);
+            apu.take_samples().is_empty()
+        }));
+
+        apu.set_sample_rate(48000);
+        (0..CYCLES_PER_SECOND / 16).for_each(|_| apu.update(0));
+        assert_eq!(apu.take_samples().len(), 3000);
+    }
+
+    #[test]
+    fn test_panning_and_volume() {
+        let mut apu = powered_apu();
+        apu.set_sample_rate(CYCLES_PER_SECOND);
+        // pulse 1 at full volume on the left only, at half the left volume
+        apu.write_register(NR50_ADDRESS, 0x30);
+        apu.write_register(NR51_ADDRESS, 0x10);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+
+        apu.update(0);
+        let sample = apu.take_samples()[0];
+        // the 12.5% duty starts low, so the dac outputs -1
+        assert_eq!(sample.left, -1.0 / 4.0 / 2.0);
+        assert_eq!(sample.right, 0.0);
+    }
+
+    #[test]
+    fn test_stems() {
+        let mut apu = powered_apu();
+        apu.set_sample_rate(CYCLES_PER_SECOND);
+        // pulse 1 on the left and pulse 2 on the right
+        apu.write_register(NR50_ADDRESS, 0x77);
+        apu.write_register(NR51_ADDRESS, 0x12);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+        apu.write_register(NR22_ADDRESS, 0xF0);
+        apu.write_register(NR24_ADDRESS, 0x80);
+
+        apu.update(0);
+        assert!(apu.take_stems().is_empty());
+        apu.set_stems(true);
+        apu.update(0);
+        let left = Sample {
+            left: -0.25,
+            right: 0.0,
+        };
+        let right = Sample {
+            left: 0.0,
+            right: -0.25,
+        };
+        let silent = Sample::default();
+        assert_eq!(apu.take_stems(), [[left, right, silent, silent]]);
+        let mixed = Mixer::new().mix(&[left, right, silent, silent]);
+        assert_eq!(apu.take_samples()[1], mixed);
+    }
+
+    #[test]
+    fn test_mixer_leaves_stems() {
+        let mut apu = powered_apu();
+        apu.set_sample_rate(CYCLES_PER_SECOND);
+        apu.set_stems(true);
+        apu.write_register(NR50_ADDRESS, 0x77);
+        apu.write_register(NR51_ADDRESS, 0x11);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+        let mut mixer = Mixer::new();
+        mixer.toggle_mute(0);
+        apu.set_mixer(mixer);
+
+        apu.update(0);
+        assert_eq!(apu.take_samples(), [Sample::default()]);
+        assert_eq!(apu.take_stems()[0][0].left, -0.25);
+    }
+
+    #[test]
+    fn test_high_passed_stems_add_up_to_the_mix() {
+        let mut apu = powered_apu();
+        apu.set_sample_rate(CYCLES_PER_SECOND);
+        apu.set_stems(true);
+        apu.write_register(NR50_ADDRESS, 0x77);
+        apu.write_register(NR51_ADDRESS, 0x12);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+        apu.write_register(NR22_ADDRESS, 0xF0);
+        apu.write_register(NR24_ADDRESS, 0x80);
+        let mut mixer = Mixer::new();
+        mixer.high_pass = true;
+        apu.set_mixer(mixer);
+
+        (0..1000).for_each(|_| apu.update(0));
+        let mixed = apu.take_samples();
+        let stems = apu.take_stems();
+        // the steady level drains away rather than staying at the first sample's
+        assert!(mixed[999].left > mixed[0].left);
+        for (mixed, stems) in mixed.iter().zip(&stems) {
+            let stem_mix = Mixer::new().mix(stems);
+            assert!((mixed.left - stem_mix.left).abs() < 1e-6);
+            assert!((mixed.right - stem_mix.right).abs() < 1e-6);
+        }
+    }
+
+    // pulse 1 with one length clock left
+    fn play_short_pulse(apu: &mut Apu) {
+        apu.write_register(NR11_ADDRESS, 63);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0xC0);
+    }
+
+    #[test]
+    fn test_frame_sequencer_follows_div() {
+        let mut apu = powered_apu();
+        play_short_pulse(&mut apu);
+
+        (0..10000).for_each(|_| apu.update(0));
+        assert!(apu.pulse1.enabled);
+        // step 0 clocks lengths
+        apu.update(1);
+        assert!(!apu.pulse1.enabled);
+    }
+
+    #[test]
+    fn test_extra_length_clock() {
+        let mut apu = powered_apu();
+        apu.update(1);
+        apu.write_register(NR11_ADDRESS, 63);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0x80);
+        assert!(apu.pulse1.enabled);
+
+        // step 1 is next, which doesn't clock lengths, so enabling the length clocks it
+        apu.write_register(NR14_ADDRESS, 0x40);
+        assert!(!apu.pulse1.enabled);
+    }
+
+    #[test]
+    fn test_dmg_keeps_lengths_when_powered_off() {
+        let mut apu = powered_apu();
+        apu.write_register(NR52_ADDRESS, 0);
+        apu.write_register(NR11_ADDRESS, 0b11_111111);
+        apu.write_register(NR52_ADDRESS, POWER_BIT);
+
+        // the duty wasn't written
+        assert_eq!(apu.read_register(NR11_ADDRESS), 0x3F);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0xC0);
+        apu.update(1);
+        assert!(!apu.pulse1.enabled);
+    }
+
+    #[test]
+    fn test_cgb_clears_lengths_when_powered_off() {
+        let mut apu = powered_apu();
+        apu.set_cgb_mode(true);
+        apu.write_register(NR11_ADDRESS, 63);
+        apu.write_register(NR52_ADDRESS, 0);
+        apu.write_register(NR11_ADDRESS, 63);
+        apu.write_register(NR52_ADDRESS, POWER_BIT);
+
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0xC0);
+        apu.update(1);
+        assert!(apu.pulse1.enabled);
+    }
+}
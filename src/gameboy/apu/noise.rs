@@ -0,0 +1,157 @@
+use super::{envelope::Envelope, length::Length};
+
+const LENGTH: u16 = 64;
+const DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+// pseudo random noise from a 15-bit linear feedback shift register, or 7-bit for a more
+// tonal sound
+pub struct Noise {
+    pub enabled: bool,
+    shift: u8,
+    short: bool,
+    divisor: u16,
+    // in t-cycles, the lfsr moves every divisor << shift
+    timer: u32,
+    lfsr: u16,
+    pub length: Length,
+    envelope: Envelope,
+}
+
+impl Noise {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            shift: 0,
+            short: false,
+            divisor: DIVISORS[0],
+            timer: 0,
+            lfsr: 0,
+            length: Length::new(LENGTH),
+            envelope: Envelope::new(),
+        }
+    }
+
+    // register 0 to 4 of the channel, NR40 (unused) to NR44
+    pub fn write(&mut self, register: u16, byte: u8, extra_length_clock: bool) {
+        match register {
+            1 => self.length.load(byte & 0b0011_1111),
+            2 => {
+                self.envelope.write(byte, self.enabled);
+                if !self.is_dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => {
+                self.shift = byte >> 4;
+                self.short = byte & 0b1000 != 0;
+                self.divisor = DIVISORS[(byte & 0b111) as usize];
+            }
+            4 => {
+                let trigger = byte & 0b1000_0000 != 0;
+                let ran_out = self
+                    .length
+                    .write_enable(byte & 0b0100_0000 != 0, extra_length_clock);
+                if ran_out && !trigger {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger(extra_length_clock);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.envelope.is_dac_enabled()
+    }
+
+    pub fn step(&mut self) {
+        let mut cycles = 4;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.shift_lfsr();
+        }
+        self.timer -= cycles;
+    }
+
+    // bit 0 clear means high
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 1 != 0 {
+            return 0;
+        }
+        self.envelope.volume()
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    fn trigger(&mut self, extra_length_clock: bool) {
+        self.enabled = self.is_dac_enabled();
+        self.length.trigger(extra_length_clock);
+        self.timer = self.period();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+    }
+
+    // the xor of the bottom two bits goes in at the top, and also into bit 6 in 7-bit mode
+    fn shift_lfsr(&mut self) {
+        let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (bit << 14);
+        if self.short {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (bit << 6);
+        }
+    }
+
+    fn period(&self) -> u32 {
+        (self.divisor as u32) << self.shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lfsr_period(short: bool) -> usize {
+        let mut noise = Noise::new();
+        noise.write(2, 0xF0, false);
+        noise.write(3, if short { 0b1000 } else { 0 }, false);
+        noise.write(4, 0x80, false);
+        // 0x7FFF itself isn't part of the 7-bit sequence
+        (0..15).for_each(|_| noise.shift_lfsr());
+        let start = noise.lfsr;
+        (1..)
+            .find(|_| {
+                noise.shift_lfsr();
+                noise.lfsr == start
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lfsr_periods() {
+        assert_eq!(lfsr_period(false), 32767);
+        assert_eq!(lfsr_period(true), 127);
+    }
+
+    #[test]
+    fn test_output() {
+        let mut noise = Noise::new();
+        noise.write(2, 0xA0, false);
+        noise.write(4, 0x80, false);
+        assert_eq!(noise.output(), 0);
+
+        // 8 t-cycles a shift, with 0x7FFF the first shift clears bit 14 and it takes 14 more
+        // for that zero to reach bit 0
+        (0..30).for_each(|_| noise.step());
+        assert_eq!(noise.output(), 0xA);
+    }
+}
@@ -0,0 +1,192 @@
+use super::{envelope::Envelope, length::Length, sweep::Sweep};
+
+const LENGTH: u16 = 64;
+const DUTY_CYCLES: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1], // 12.5%
+    [1, 0, 0, 0, 0, 0, 0, 1], // 25%
+    [1, 0, 0, 0, 0, 1, 1, 1], // 50%
+    [0, 1, 1, 1, 1, 1, 1, 0], // 75%
+];
+
+// the two square wave channels, only the first one has a sweep
+pub struct Pulse {
+    pub enabled: bool,
+    duty: usize,
+    duty_position: usize,
+    frequency: u16,
+    timer: u16,
+    pub length: Length,
+    envelope: Envelope,
+    sweep: Option<Sweep>,
+}
+
+impl Pulse {
+    pub fn new(has_sweep: bool) -> Self {
+        Self {
+            enabled: false,
+            duty: 0,
+            duty_position: 0,
+            frequency: 0,
+            timer: 0,
+            length: Length::new(LENGTH),
+            envelope: Envelope::new(),
+            sweep: has_sweep.then(Sweep::new),
+        }
+    }
+
+    // register 0 to 4 of the channel, NRx0 to NRx4
+    pub fn write(&mut self, register: u16, byte: u8, extra_length_clock: bool) {
+        match register {
+            0 => {
+                if let Some(sweep) = self.sweep.as_mut() {
+                    if sweep.write(byte) {
+                        self.enabled = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = (byte >> 6) as usize;
+                self.length.load(byte & 0b0011_1111);
+            }
+            2 => {
+                self.envelope.write(byte, self.enabled);
+                if !self.is_dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | byte as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((byte as u16 & 0b111) << 8);
+                let trigger = byte & 0b1000_0000 != 0;
+                let ran_out = self
+                    .length
+                    .write_enable(byte & 0b0100_0000 != 0, extra_length_clock);
+                if ran_out && !trigger {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger(extra_length_clock);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.envelope.is_dac_enabled()
+    }
+
+    // the duty step moves every (2048 - frequency) m-cycles
+    pub fn step(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = self.period();
+            self.duty_position = (self.duty_position + 1) % 8;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        DUTY_CYCLES[self.duty][self.duty_position] * self.envelope.volume()
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if let Some(sweep) = self.sweep.as_mut() {
+            match sweep.clock(self.frequency) {
+                Some(frequency) => self.frequency = frequency,
+                None => self.enabled = false,
+            }
+        }
+    }
+
+    fn trigger(&mut self, extra_length_clock: bool) {
+        self.enabled = self.is_dac_enabled();
+        self.length.trigger(extra_length_clock);
+        self.timer = self.period();
+        self.envelope.trigger();
+        if let Some(sweep) = self.sweep.as_mut() {
+            if sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn period(&self) -> u16 {
+        2048 - self.frequency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(pulse: &mut Pulse, steps: usize) -> Vec<u8> {
+        (0..steps)
+            .map(|_| {
+                pulse.step();
+                pulse.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_duty() {
+        let mut pulse = Pulse::new(false);
+        pulse.write(1, 0b10_000000, false);
+        pulse.write(2, 0xF0, false);
+        pulse.write(3, 0xFF, false);
+        pulse.write(4, 0x87, false);
+
+        // a period of one m-cycle, so a new duty step every step
+        assert_eq!(outputs(&mut pulse, 8), [0, 0, 0, 0, 15, 15, 15, 15]);
+    }
+
+    #[test]
+    fn test_period() {
+        let mut pulse = Pulse::new(false);
+        pulse.write(1, 0b11_000000, false);
+        pulse.write(2, 0xF0, false);
+        pulse.write(3, 0xFE, false);
+        pulse.write(4, 0x87, false);
+
+        assert_eq!(outputs(&mut pulse, 4), [0, 15, 15, 15]);
+    }
+
+    #[test]
+    fn test_dac_off_disables() {
+        let mut pulse = Pulse::new(true);
+        pulse.write(2, 0xF0, false);
+        pulse.write(4, 0x80, false);
+        assert!(pulse.enabled);
+
+        pulse.write(2, 0x00, false);
+        assert!(!pulse.enabled);
+        pulse.write(4, 0x80, false);
+        assert!(!pulse.enabled);
+    }
+
+    #[test]
+    fn test_length_expires() {
+        let mut pulse = Pulse::new(false);
+        pulse.write(1, 62, false);
+        pulse.write(2, 0xF0, false);
+        pulse.write(4, 0xC0, false);
+
+        pulse.clock_length();
+        assert!(pulse.enabled);
+        pulse.clock_length();
+        assert!(!pulse.enabled);
+    }
+}
@@ -0,0 +1,125 @@
+const MAX_FREQUENCY: u16 = 2047;
+
+// NR10, pulse 1's frequency sweep clocked at 128 Hz. it works on a shadow copy of the
+// frequency and turns the channel off if the frequency would overflow
+pub struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    enabled: bool,
+    shadow: u16,
+    timer: u8,
+    // a negated calculation since the last trigger, see write
+    negated: bool,
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Self {
+            period: 0,
+            negate: false,
+            shift: 0,
+            enabled: false,
+            shadow: 0,
+            timer: 0,
+            negated: false,
+        }
+    }
+
+    // true if the channel should be disabled, which happens when negate is cleared after
+    // it's been used in a calculation
+    pub fn write(&mut self, byte: u8) -> bool {
+        self.period = (byte >> 4) & 0b111;
+        self.shift = byte & 0b111;
+        let negate = byte & 0b1000 != 0;
+        let disable = self.negate && !negate && self.negated;
+        self.negate = negate;
+        disable
+    }
+
+    // true if the channel overflowed straight away
+    pub fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow = frequency;
+        self.timer = self.reload_period();
+        self.enabled = self.period != 0 || self.shift != 0;
+        self.negated = false;
+        self.shift != 0 && self.calculate() > MAX_FREQUENCY
+    }
+
+    // returns the new frequency, or none if the channel overflowed and should be disabled
+    pub fn clock(&mut self, frequency: u16) -> Option<u16> {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return Some(frequency);
+        }
+        self.timer = self.reload_period();
+        if !self.enabled || self.period == 0 {
+            return Some(frequency);
+        }
+
+        let new = self.calculate();
+        if new > MAX_FREQUENCY {
+            return None;
+        }
+        if self.shift == 0 {
+            return Some(frequency);
+        }
+        self.shadow = new;
+        // the new frequency is checked again straight away but not used
+        (self.calculate() <= MAX_FREQUENCY).then_some(new)
+    }
+
+    fn calculate(&mut self) -> u16 {
+        let change = self.shadow >> self.shift;
+        if self.negate {
+            self.negated = true;
+            self.shadow - change
+        } else {
+            self.shadow + change
+        }
+    }
+
+    // a period of 0 is treated as 8 by the timer
+    fn reload_period(&self) -> u8 {
+        if self.period == 0 {
+            8
+        } else {
+            self.period
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_up() {
+        let mut sweep = Sweep::new();
+        sweep.write(0x11);
+        assert!(!sweep.trigger(0x100));
+
+        assert_eq!(sweep.clock(0x100), Some(0x180));
+        assert_eq!(sweep.clock(0x180), Some(0x240));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut sweep = Sweep::new();
+        sweep.write(0x11);
+        assert!(sweep.trigger(0x700));
+
+        sweep.trigger(0x500);
+        assert_eq!(sweep.clock(0x500), None);
+    }
+
+    #[test]
+    fn test_clearing_negate_after_use_disables() {
+        let mut sweep = Sweep::new();
+        sweep.write(0x19);
+        sweep.trigger(0x400);
+        assert_eq!(sweep.clock(0x400), Some(0x200));
+
+        assert!(sweep.write(0x11));
+    }
+}
@@ -0,0 +1,174 @@
+use super::length::Length;
+
+const LENGTH: u16 = 256;
+const WAVE_RAM_SIZE: usize = 16;
+const SAMPLES: usize = WAVE_RAM_SIZE * 2;
+
+// plays the 32 4-bit samples in wave ram, high nibble first
+pub struct Wave {
+    pub enabled: bool,
+    dac_enabled: bool,
+    // how far each sample is shifted down, 4 mutes it
+    volume_shift: u8,
+    frequency: u16,
+    // in t-cycles, a sample lasts (2048 - frequency) * 2
+    timer: u16,
+    position: usize,
+    sample: u8,
+    pub length: Length,
+    pub ram: [u8; WAVE_RAM_SIZE],
+}
+
+impl Wave {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            dac_enabled: false,
+            volume_shift: 4,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+            length: Length::new(LENGTH),
+            ram: [0; WAVE_RAM_SIZE],
+        }
+    }
+
+    // register 0 to 4 of the channel, NR30 to NR34
+    pub fn write(&mut self, register: u16, byte: u8, extra_length_clock: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = byte & 0b1000_0000 != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(byte),
+            2 => {
+                self.volume_shift = match (byte >> 5) & 0b11 {
+                    0 => 4,
+                    1 => 0,
+                    2 => 1,
+                    _ => 2,
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | byte as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((byte as u16 & 0b111) << 8);
+                let trigger = byte & 0b1000_0000 != 0;
+                let ran_out = self
+                    .length
+                    .write_enable(byte & 0b0100_0000 != 0, extra_length_clock);
+                if ran_out && !trigger {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger(extra_length_clock);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    // a fast wave can move on more than one sample in an m-cycle
+    pub fn step(&mut self) {
+        let mut cycles = 4;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.position = (self.position + 1) % SAMPLES;
+            self.sample = self.ram[self.position / 2];
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        let nibble = if self.position & 1 == 0 {
+            self.sample >> 4
+        } else {
+            self.sample & 0x0F
+        };
+        nibble >> self.volume_shift
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    // the first sample played is the one after position 0, the sample buffer isn't
+    // refilled until then
+    fn trigger(&mut self, extra_length_clock: bool) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger(extra_length_clock);
+        self.timer = self.period();
+        self.position = 0;
+    }
+
+    fn period(&self) -> u16 {
+        (2048 - self.frequency) * 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(wave: &mut Wave, volume: u8) {
+        wave.ram[0] = 0x8F;
+        wave.ram[1] = 0x42;
+        wave.write(0, 0x80, false);
+        wave.write(2, volume << 5, false);
+        // 4 t-cycles a sample, one each step
+        wave.write(3, 0xFE, false);
+        wave.write(4, 0x87, false);
+    }
+
+    fn outputs(wave: &mut Wave) -> Vec<u8> {
+        (0..3)
+            .map(|_| {
+                wave.step();
+                wave.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_samples() {
+        let mut wave = Wave::new();
+        play(&mut wave, 1);
+
+        assert_eq!(outputs(&mut wave), [0xF, 0x4, 0x2]);
+    }
+
+    #[test]
+    fn test_volume_shift() {
+        let mut wave = Wave::new();
+        play(&mut wave, 3);
+        assert_eq!(outputs(&mut wave), [0x3, 0x1, 0x0]);
+
+        play(&mut wave, 0);
+        assert_eq!(outputs(&mut wave), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fast_wave_skips_samples() {
+        let mut wave = Wave::new();
+        play(&mut wave, 1);
+        // 2 t-cycles a sample, two each step
+        wave.write(3, 0xFF, false);
+
+        wave.step();
+        assert_eq!(wave.output(), 0xF);
+        wave.step();
+        assert_eq!(wave.output(), 0x2);
+    }
+}
@@ -1,21 +1,26 @@
 use super::{
+    apu::{Apu, Mixer, Sample, Stems},
     bios::{Bios, BIOS_ADDRESS_END, BIOS_ADDRESS_START, BIOS_MAPPED_ADDRESS},
+    buttons::Buttons,
     dma::{Dma, DMA_ADDRESS},
+    hdma::{Hdma, HDMA1_ADDRESS, HDMA4_ADDRESS, HDMA5_ADDRESS},
     joypad::{Joypad, JOYPAD_ADDRESS},
+    sgb::Sgb,
+    speed::{Speed, KEY1_ADDRESS},
+    wram::{Wram, ECHO_BEGIN, ECHO_END, SVBK_ADDRESS, WRAM_BEGIN, WRAM_END},
     GameboyCycles,
 };
 use super::{
-    gpu::{new_ppu, Gpu, OAM_BEGIN, OAM_END, VRAM_BEGIN, VRAM_END},
+    gpu::{new_ppu, ColorScheme, Gpu, PpuState, OAM_BEGIN, OAM_END, VRAM_BEGIN, VRAM_END},
     interrupts::{InterruptHandler, IE_ADDRESS, IF_ADDRESS},
-    timer::Timer,
+    timer::{Timer, DIV_ADDRESS},
 };
-use crate::emu::{controller::Controller, display::Display, rom::Rom};
+use crate::emu::{display::Display, rom::Rom};
 
 pub const HRAM_ADDRESS_START: u16 = 0xFF80;
 // const HRAM_ADDRESS_END: u16 = 0xFFFE;
-const UNUSED_ADDRESSES: [u16; 13] = [
-    0xFF03, 0xFF08, 0xFF09, 0xFF0A, 0xFF0B, 0xFF0C, 0xFF0D, 0xFF0E, 0xFF15, 0xFF1F, 0xFF27, 0xFF28,
-    0xFF29,
+const UNUSED_ADDRESSES: [u16; 8] = [
+    0xFF03, 0xFF08, 0xFF09, 0xFF0A, 0xFF0B, 0xFF0C, 0xFF0D, 0xFF0E,
 ];
 
 #[cfg(test)]
@@ -23,11 +28,25 @@ use mockall::automock;
 #[cfg_attr(test, automock)]
 pub trait Bus {
     fn update_dma(&mut self);
+    fn update_hdma(&mut self);
+    fn is_hdma_transferring(&self) -> bool;
+    fn is_double_speed(&self) -> bool;
+    fn switch_speed(&mut self);
+    fn is_bios_mapped(&self) -> bool;
     fn update_gpu(&mut self);
+    fn is_vblank_start(&self) -> bool;
     fn update_timer(&mut self);
+    fn update_apu(&mut self);
+    fn set_sample_rate(&mut self, sample_rate: u32);
+    fn take_samples(&mut self) -> Vec<Sample>;
+    fn set_stems(&mut self, keep_stems: bool);
+    fn take_stems(&mut self) -> Vec<Stems>;
+    fn set_mixer(&mut self, mixer: Mixer);
     fn update_ime(&mut self);
-    fn update_joypad(&mut self, controller: &Box<dyn Controller>);
-    fn render_display(&mut self, display: &mut Box<dyn Display>);
+    fn update_joypad(&mut self, buttons: &Buttons);
+    fn render_display(&mut self, display: &mut dyn Display);
+    fn set_color_scheme(&mut self, scheme: ColorScheme);
+    fn ppu_state(&self) -> PpuState;
     fn schedule_ime(&mut self);
     fn disable_ime(&mut self);
     fn has_interrupt_pending(&self) -> bool;
@@ -44,11 +63,17 @@ pub fn new_address_bus() -> Box<dyn Bus> {
 pub struct AddressBus {
     bios: Bios,
     timer: Timer,
+    apu: Apu,
     dma: Option<Dma>,
+    hdma: Option<Hdma>,
+    speed: Speed,
+    cgb: bool,
     interrupt_handler: InterruptHandler,
     memory: [u8; 0x10000],
+    wram: Wram,
     gpu: Box<dyn Gpu>,
     joypad: Joypad,
+    sgb: Option<Sgb>,
 }
 
 impl AddressBus {
@@ -56,6 +81,7 @@ impl AddressBus {
         let gpu = new_ppu();
         let bios = Bios::new();
         let dma = Some(Dma::new());
+        let hdma = Some(Hdma::new());
         let joypad = Joypad::new();
         let timer = Timer::new();
         let interrupt_handler = InterruptHandler::new();
@@ -63,11 +89,17 @@ impl AddressBus {
         Box::new(Self {
             bios,
             timer,
+            apu: Apu::new(),
             dma,
+            hdma,
+            speed: Speed::new(),
+            cgb: false,
             interrupt_handler,
             memory: [0; 0x10000],
+            wram: Wram::new(),
             gpu,
             joypad,
+            sgb: None,
         })
     }
 }
@@ -87,26 +119,95 @@ impl Bus for AddressBus {
         self.dma = Some(dma);
     }
 
+    fn update_hdma(&mut self) {
+        let mut hdma = self.hdma.take().unwrap();
+        hdma.update(self);
+        self.hdma = Some(hdma);
+    }
+
+    fn is_hdma_transferring(&self) -> bool {
+        self.hdma.as_ref().unwrap().is_transferring()
+    }
+
+    fn is_double_speed(&self) -> bool {
+        self.speed.double
+    }
+
+    // the divider is reset along with the switch
+    fn switch_speed(&mut self) {
+        if self.cgb && self.speed.switch() {
+            self.timer.double_speed = self.speed.double;
+            self.timer.write_register(DIV_ADDRESS, 0);
+        }
+    }
+
     fn update_ime(&mut self) {
         self.interrupt_handler.update_ime();
     }
 
     fn update_gpu(&mut self) {
         self.gpu.update(&mut self.interrupt_handler);
+        if self.gpu.is_hblank_start() {
+            self.hdma.as_mut().unwrap().hblank();
+        }
     }
 
-    fn update_joypad(&mut self, controller: &Box<dyn Controller>) {
-        self.joypad.update(controller);
+    fn is_vblank_start(&self) -> bool {
+        self.gpu.is_vblank_start()
     }
 
-    fn render_display(&mut self, display: &mut Box<dyn Display>) {
-        self.gpu.render_display(display);
+    fn is_bios_mapped(&self) -> bool {
+        self.bios.mapped
+    }
+
+    fn update_joypad(&mut self, buttons: &Buttons) {
+        self.joypad.update(buttons);
+    }
+
+    fn render_display(&mut self, display: &mut dyn Display) {
+        match self.sgb.as_mut() {
+            Some(sgb) => sgb.render_display(self.gpu.as_ref(), display),
+            None => self.gpu.render_display(display),
+        }
+    }
+
+    fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        self.gpu.set_color_scheme(scheme);
+    }
+
+    fn ppu_state(&self) -> PpuState {
+        self.gpu.state()
     }
 
     fn update_timer(&mut self) {
         self.timer.update(&mut self.interrupt_handler);
     }
 
+    fn update_apu(&mut self) {
+        let div_apu_events = self.timer.take_div_apu_events();
+        self.apu.update(div_apu_events);
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.apu.set_sample_rate(sample_rate);
+    }
+
+    fn take_samples(&mut self) -> Vec<Sample> {
+        self.apu.take_samples()
+    }
+
+    fn set_stems(&mut self, keep_stems: bool) {
+        self.apu.set_stems(keep_stems);
+    }
+
+    fn take_stems(&mut self) -> Vec<Stems> {
+        self.apu.take_stems()
+    }
+
+    fn set_mixer(&mut self, mixer: Mixer) {
+        self.apu.set_mixer(mixer);
+    }
+
     fn schedule_ime(&mut self) {
         self.interrupt_handler.schedule_ime();
     }
@@ -117,10 +218,16 @@ impl Bus for AddressBus {
 
     fn read_byte(&mut self, address: u16) -> u8 {
         match address {
+            address if self.gpu.handles(address) => self.gpu.read_register(address),
+            HDMA1_ADDRESS..=HDMA5_ADDRESS if self.cgb => {
+                self.hdma.as_ref().unwrap().read_register(address)
+            }
+            KEY1_ADDRESS if self.cgb => self.speed.read(),
+            SVBK_ADDRESS if self.cgb => self.wram.read_svbk(),
             0xFF4C..=0xFF7F => 0xFF,
             address if UNUSED_ADDRESSES.contains(&address) => 0xFF,
-            address if self.gpu.handles(address) => self.gpu.read_register(address),
             address if self.timer.handles(address) => self.timer.read_register(address),
+            address if self.apu.handles(address) => self.apu.read_register(address),
             BIOS_ADDRESS_START..=BIOS_ADDRESS_END => {
                 if self.bios.mapped {
                     self.bios.data[address as usize]
@@ -131,12 +238,6 @@ impl Bus for AddressBus {
             IF_ADDRESS => self.interrupt_handler.read_flags(),
             IE_ADDRESS => self.interrupt_handler.read_enable(),
             0xFF02 => 0xFF,
-            0xFF10 => self.memory[address as usize] | 0b1_0000000,
-            0xFF1A => self.memory[address as usize] | 0b0_1111111,
-            0xFF1C => self.memory[address as usize] | 0b1_00_11111,
-            0xFF20 => self.memory[address as usize] | 0b11_000000,
-            0xFF23 => self.memory[address as usize] | 0b00_111111,
-            0xFF26 => self.memory[address as usize] | 0b0_111_0000,
             0xFEA0..=0xFEFF => 0,
             JOYPAD_ADDRESS => self.joypad.read(),
             DMA_ADDRESS => {
@@ -144,6 +245,7 @@ impl Bus for AddressBus {
                 dma.upper
             }
             VRAM_BEGIN..=VRAM_END => self.gpu.read_vram(address - VRAM_BEGIN),
+            WRAM_BEGIN..=WRAM_END | ECHO_BEGIN..=ECHO_END => self.wram.read(address),
             OAM_BEGIN..=OAM_END => self.gpu.read_oam(address - OAM_BEGIN),
             _ => self.memory[address as usize],
         }
@@ -154,19 +256,36 @@ impl Bus for AddressBus {
             BIOS_MAPPED_ADDRESS => self.bios.mapped = false,
             0x0000..=0x7FFF => (), // ignore writes to rom
             0xFEA0..=0xFEFF => (), // prohibited
+            address if self.gpu.handles(address) => self.gpu.write_register(address, byte),
+            HDMA5_ADDRESS if self.cgb => {
+                let lcd_enabled = self.gpu.is_lcd_enabled();
+                self.hdma.as_mut().unwrap().start(byte, lcd_enabled)
+            }
+            HDMA1_ADDRESS..=HDMA4_ADDRESS if self.cgb => {
+                self.hdma.as_mut().unwrap().write_register(address, byte)
+            }
+            KEY1_ADDRESS if self.cgb => self.speed.write(byte),
+            SVBK_ADDRESS if self.cgb => self.wram.write_svbk(byte),
             0xFF4C..=0xFF7F => (),
             address if UNUSED_ADDRESSES.contains(&address) => (),
-            address if self.gpu.handles(address) => self.gpu.write_register(address, byte),
             address if self.timer.handles(address) => self.timer.write_register(address, byte),
+            address if self.apu.handles(address) => self.apu.write_register(address, byte),
             IF_ADDRESS => self.interrupt_handler.write_flags(byte),
             IE_ADDRESS => self.interrupt_handler.write_enable(byte),
             0xFF02 => (),
-            JOYPAD_ADDRESS => self.joypad.select(byte),
+            JOYPAD_ADDRESS => {
+                self.joypad.select(byte);
+                if let Some(sgb) = self.sgb.as_mut() {
+                    sgb.write_joypad(byte, self.gpu.as_ref());
+                    self.joypad.set_players(sgb.players());
+                }
+            }
             DMA_ADDRESS => {
                 let dma = self.dma.as_mut().unwrap();
                 dma.start(byte);
             }
             VRAM_BEGIN..=VRAM_END => self.gpu.write_vram(address - VRAM_BEGIN, byte),
+            WRAM_BEGIN..=WRAM_END | ECHO_BEGIN..=ECHO_END => self.wram.write(address, byte),
             OAM_BEGIN..=OAM_END => self.gpu.write_oam(address - OAM_BEGIN, byte),
             _ => {
                 self.memory[address as usize] = byte;
@@ -178,5 +297,10 @@ impl Bus for AddressBus {
         let bytes = rom.data.as_slice();
         let end = bytes.len();
         self.memory[0..end].copy_from_slice(bytes);
+        self.cgb = rom.is_cgb();
+        self.gpu.set_cgb_mode(self.cgb);
+        self.apu.set_cgb_mode(self.cgb);
+        // cgb games that also support the sgb run as cgb games
+        self.sgb = (!self.cgb && rom.is_sgb()).then(Sgb::new);
     }
 }
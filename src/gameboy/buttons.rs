@@ -0,0 +1,56 @@
+const DOWN_BIT: u8 = 1 << 3;
+const UP_BIT: u8 = 1 << 2;
+const LEFT_BIT: u8 = 1 << 1;
+const RIGHT_BIT: u8 = 1;
+const START_BIT: u8 = 1 << 3;
+const SELECT_BIT: u8 = 1 << 2;
+const B_BIT: u8 = 1 << 1;
+const A_BIT: u8 = 1;
+
+// which buttons are held, filled in by whatever frontend reads the input
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Buttons {
+    pub start: bool,
+    pub select: bool,
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub a: bool,
+    pub b: bool,
+}
+
+impl Buttons {
+    // the low nibble of the joypad register, 0 means pressed
+    pub fn actions(&self) -> u8 {
+        !((if self.start { START_BIT } else { 0 })
+            | (if self.select { SELECT_BIT } else { 0 })
+            | (if self.b { B_BIT } else { 0 })
+            | (if self.a { A_BIT } else { 0 }))
+    }
+
+    pub fn directions(&self) -> u8 {
+        !((if self.down { DOWN_BIT } else { 0 })
+            | (if self.up { UP_BIT } else { 0 })
+            | (if self.left { LEFT_BIT } else { 0 })
+            | (if self.right { RIGHT_BIT } else { 0 }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_low() {
+        let buttons = Buttons {
+            start: true,
+            left: true,
+            ..Default::default()
+        };
+
+        assert_eq!(buttons.actions(), !START_BIT);
+        assert_eq!(buttons.directions(), !LEFT_BIT);
+        assert_eq!(Buttons::default().actions(), 0xFF);
+    }
+}
@@ -0,0 +1,58 @@
+const PALETTE_MASK: u8 = 0b0000_0111;
+const BANK_BIT: u8 = 1 << 3;
+const X_FLIP_BIT: u8 = 1 << 5;
+const Y_FLIP_BIT: u8 = 1 << 6;
+const PRIORITY_BIT: u8 = 1 << 7;
+
+// cgb bg map attributes, stored in vram bank 1 at the same address as the tile index.
+// on dmg every tile uses the default of 0
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TileAttributes {
+    byte: u8,
+}
+
+impl std::convert::From<u8> for TileAttributes {
+    fn from(byte: u8) -> Self {
+        Self { byte }
+    }
+}
+
+impl TileAttributes {
+    pub fn palette(&self) -> u8 {
+        self.byte & PALETTE_MASK
+    }
+
+    pub fn bank(&self) -> usize {
+        (self.byte & BANK_BIT != 0) as usize
+    }
+
+    pub fn is_x_flipped(&self) -> bool {
+        self.byte & X_FLIP_BIT != 0
+    }
+
+    pub fn is_y_flipped(&self) -> bool {
+        self.byte & Y_FLIP_BIT != 0
+    }
+
+    // bg colors 1-3 are drawn over every sprite
+    pub fn has_priority(&self) -> bool {
+        self.byte & PRIORITY_BIT != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attributes() {
+        let attributes = TileAttributes::from(0b1010_1101);
+
+        assert_eq!(attributes.palette(), 5);
+        assert_eq!(attributes.bank(), 1);
+        assert!(attributes.is_x_flipped());
+        assert!(!attributes.is_y_flipped());
+        assert!(attributes.has_priority());
+        assert_eq!(TileAttributes::default().bank(), 0);
+    }
+}
@@ -0,0 +1,250 @@
+use super::{
+    palette::{ColorScheme, Shades},
+    palette_ram::rgb555_color,
+};
+use crate::gameboy::Buttons;
+
+const SCHEME_NAME: &str = "cgb";
+const DEFAULT_COMBINATION: usize = 0;
+const FIRST_DUPLICATE_CHECKSUM: usize = 65;
+const FOURTH_LETTER: usize = 3;
+
+// the cgb boot rom's 30 palettes of 4 colors. kept flat as a few combinations start part
+// way through a palette
+#[rustfmt::skip]
+const PALETTE_COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000,
+    0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000,
+    0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000,
+    0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+    0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000,
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000,
+    0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000,
+    0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000,
+    0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+    0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
+// color offsets of the object0, object1 and background palettes
+const fn palettes(object0: usize, object1: usize, background: usize) -> [usize; 3] {
+    [object0 * 4, object1 * 4, background * 4]
+}
+
+const COMBINATIONS: [[usize; 3]; 51] = [
+    palettes(4, 4, 29),   // right + a, also used for unknown games
+    palettes(18, 18, 18), // right
+    palettes(20, 20, 20),
+    palettes(24, 24, 24), // down + a
+    palettes(9, 9, 9),
+    palettes(0, 0, 0),    // up
+    palettes(27, 27, 27), // right + b
+    palettes(5, 5, 5),    // left + b
+    palettes(12, 12, 12), // down
+    palettes(26, 26, 26),
+    palettes(16, 8, 8),
+    palettes(4, 28, 28),
+    palettes(4, 2, 2),
+    palettes(3, 4, 4),
+    palettes(4, 29, 29),
+    palettes(28, 4, 28),
+    palettes(2, 17, 2),
+    palettes(16, 16, 8),
+    palettes(4, 4, 7),
+    palettes(4, 4, 18),
+    palettes(4, 4, 20),
+    palettes(19, 19, 9),
+    [15, 15, 44],
+    palettes(17, 17, 2),
+    palettes(4, 4, 2),
+    palettes(4, 4, 3),
+    palettes(28, 28, 0),
+    palettes(3, 3, 0),
+    palettes(0, 0, 1), // up + b
+    palettes(18, 22, 18),
+    palettes(20, 22, 20),
+    palettes(24, 22, 24),
+    palettes(16, 22, 8),
+    palettes(17, 4, 13),
+    [111, 0, 56],
+    [111, 16, 60],
+    palettes(19, 22, 9),
+    palettes(16, 28, 10),
+    palettes(4, 23, 28),
+    palettes(17, 22, 2),
+    palettes(4, 0, 2), // left + a
+    palettes(4, 28, 3),
+    palettes(28, 3, 0),
+    palettes(3, 28, 4), // up + a
+    palettes(21, 28, 4),
+    palettes(3, 28, 0),
+    palettes(25, 3, 28),
+    palettes(0, 28, 8),
+    palettes(4, 3, 28), // left
+    palettes(28, 3, 6), // down + b
+    palettes(4, 28, 29),
+];
+
+// sums of the 16 title bytes for the nintendo games the boot rom knows. checksums from
+// FIRST_DUPLICATE_CHECKSUM on are shared between games and also need the title's 4th letter
+#[rustfmt::skip]
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B, 0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+    0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+];
+
+const DUPLICATE_FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+#[rustfmt::skip]
+const CHECKSUM_COMBINATIONS: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44,
+    21, 32, 31, 20, 5, 33, 13, 14, 5, 29, 5, 18, 9, 3, 2, 26,
+    25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34,
+    5, 42, 6, 5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0,
+    39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50, 17,
+    46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+
+// up, left, down and right held while the boot logo shows, alone, with a and with b
+const MANUAL_COMBINATIONS: [[usize; 3]; 4] = [[5, 43, 28], [48, 40, 7], [8, 3, 49], [1, 0, 6]];
+
+// the palettes a cgb picks for a dmg game. only games with a nintendo licensee are looked up
+// by their title, everything else gets the default combination
+pub fn compatibility_scheme(title: &[u8], nintendo: bool) -> ColorScheme {
+    let combination = if nintendo {
+        title_combination(title)
+    } else {
+        DEFAULT_COMBINATION
+    };
+    combination_scheme(combination)
+}
+
+pub fn manual_scheme(buttons: &Buttons) -> Option<ColorScheme> {
+    let direction = [buttons.up, buttons.left, buttons.down, buttons.right]
+        .iter()
+        .position(|held| *held)?;
+    let button = if buttons.a {
+        1
+    } else if buttons.b {
+        2
+    } else {
+        0
+    };
+    Some(combination_scheme(MANUAL_COMBINATIONS[direction][button]))
+}
+
+fn title_combination(title: &[u8]) -> usize {
+    let checksum = title.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    TITLE_CHECKSUMS
+        .iter()
+        .enumerate()
+        .position(|(index, sum)| {
+            *sum == checksum
+                && (index < FIRST_DUPLICATE_CHECKSUM
+                    || DUPLICATE_FOURTH_LETTERS[index - FIRST_DUPLICATE_CHECKSUM]
+                        == title[FOURTH_LETTER])
+        })
+        .map_or(DEFAULT_COMBINATION, |index| {
+            CHECKSUM_COMBINATIONS[index] as usize
+        })
+}
+
+fn combination_scheme(combination: usize) -> ColorScheme {
+    let [object0, object1, background] = COMBINATIONS[combination];
+    ColorScheme::new(
+        SCHEME_NAME,
+        shades(background),
+        shades(object0),
+        shades(object1),
+    )
+}
+
+fn shades(offset: usize) -> Shades {
+    Shades::new([0, 1, 2, 3].map(|color| rgb555_color(PALETTE_COLORS[offset + color])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::Color;
+
+    fn title(name: &str) -> Vec<u8> {
+        let mut title = name.as_bytes().to_vec();
+        title.resize(16, 0);
+        title
+    }
+
+    #[test]
+    fn test_title_lookup() {
+        let scheme = compatibility_scheme(&title("TETRIS"), true);
+
+        assert_eq!(scheme.background.color(1), Color::rgb(0xFF, 0xFF, 0x00));
+        assert_eq!(scheme.background.color(2), Color::rgb(0xFF, 0x00, 0x00));
+        assert_eq!(scheme.object0, scheme.background);
+    }
+
+    #[test]
+    fn test_title_lookup_fourth_letter() {
+        assert_eq!(title_combination(&title("POKEMON BLUE")), 11);
+        // same checksum as pokemon blue but a different 4th letter
+        let mut vegas = title("POKEMON BLUE");
+        vegas[FOURTH_LETTER] = b'A';
+        vegas[4] += b'E' - b'A';
+
+        assert_eq!(title_combination(&vegas), 41);
+    }
+
+    #[test]
+    fn test_unknown_and_non_nintendo_games_use_default() {
+        let default = combination_scheme(DEFAULT_COMBINATION);
+
+        assert_eq!(compatibility_scheme(&title("TETRIS"), false), default);
+        assert_eq!(compatibility_scheme(&title("GARLICKBOY"), true), default);
+        assert_eq!(default.background.color(1), Color::rgb(0x7B, 0xFF, 0x31));
+        assert_eq!(default.object1.color(1), Color::rgb(0xFF, 0x84, 0x84));
+    }
+
+    #[test]
+    fn test_manual_scheme() {
+        let left = Buttons {
+            left: true,
+            ..Default::default()
+        };
+        let grey = manual_scheme(&Buttons { b: true, ..left }).unwrap();
+        let blue = manual_scheme(&left).unwrap();
+
+        assert_eq!(grey.background.color(1), Color::rgb(0xA5, 0xA5, 0xA5));
+        assert_eq!(blue.background.color(1), Color::rgb(0x63, 0xA5, 0xFF));
+        assert_eq!(blue.object1.color(1), Color::rgb(0x7B, 0xFF, 0x31));
+        assert_eq!(
+            manual_scheme(&Buttons {
+                a: true,
+                ..Default::default()
+            }),
+            None
+        );
+    }
+}
@@ -2,8 +2,10 @@ pub(crate) struct Control {
     obj_size_16: bool,
     lcd_enabled: bool,
     window_enabled: bool,
+    window_tile_map_area: bool,
     obj_enabled: bool,
-    bg_window_priority: bool,
+    bg_window_enabled: bool,
+    bg_window_tile_data_area: bool,
     bg_tile_map_area: bool,
 }
 
@@ -16,26 +18,71 @@ impl Control {
         }
     }
 
+    // 0x8000 mode indexes tiles unsigned, 0x8800 mode is signed from 0x9000
+    pub fn tile_data_address(&self, tile: u8) -> u16 {
+        if self.bg_window_tile_data_area {
+            tile as u16 * 16
+        } else {
+            (0x1000 + tile as i8 as i16 * 16) as u16
+        }
+    }
+
+    pub fn is_bg_window_enabled(&self) -> bool {
+        self.bg_window_enabled
+    }
+
+    pub fn window_tile_map_area_address(&self) -> u16 {
+        if self.window_tile_map_area {
+            0x1C00
+        } else {
+            0x1800
+        }
+    }
+
+    pub fn is_window_enabled(&self) -> bool {
+        self.window_enabled
+    }
+
+    pub fn is_obj_enabled(&self) -> bool {
+        self.obj_enabled
+    }
+
+    pub fn obj_height(&self) -> u8 {
+        if self.obj_size_16 {
+            16
+        } else {
+            8
+        }
+    }
+
     pub fn is_lcd_enabled(&self) -> bool {
         self.lcd_enabled
     }
 
     pub fn read(&self) -> u8 {
         (if self.lcd_enabled { 1 << 7 } else { 0 })
+            | (if self.window_tile_map_area { 1 << 6 } else { 0 })
             | (if self.window_enabled { 1 << 5 } else { 0 })
+            | (if self.bg_window_tile_data_area {
+                1 << 4
+            } else {
+                0
+            })
             | (if self.bg_tile_map_area { 1 << 3 } else { 0 })
             | (if self.obj_size_16 { 1 << 2 } else { 0 })
             | (if self.obj_enabled { 1 << 1 } else { 0 })
-            | (if self.bg_window_priority { 1 } else { 0 })
+            | (if self.bg_window_enabled { 1 } else { 0 })
     }
 
     pub fn write(&mut self, control: u8) {
         self.lcd_enabled = control >> 7 & 1 == 1;
+        self.window_tile_map_area = control >> 6 & 1 == 1;
         self.window_enabled = control >> 5 & 1 == 1;
+        self.bg_window_tile_data_area = control >> 4 & 1 == 1;
         self.bg_tile_map_area = control >> 3 & 1 == 1;
         self.obj_size_16 = control >> 2 & 1 == 1;
         self.obj_enabled = control >> 1 & 1 == 1;
-        self.bg_window_priority = control & 1 == 1;
+        self.bg_window_enabled = control & 1 == 1;
     }
 
     pub fn new() -> Self {
@@ -43,9 +90,45 @@ impl Control {
             obj_size_16: false,
             lcd_enabled: false,
             window_enabled: false,
+            window_tile_map_area: false,
             obj_enabled: false,
-            bg_window_priority: false,
+            bg_window_enabled: false,
+            bg_window_tile_data_area: false,
             bg_tile_map_area: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_data_address_unsigned() {
+        let mut control = Control::new();
+        control.write(1 << 4);
+
+        assert_eq!(control.tile_data_address(0), 0x0000);
+        assert_eq!(control.tile_data_address(0x80), 0x0800);
+        assert_eq!(control.tile_data_address(0xFF), 0x0FF0);
+    }
+
+    #[test]
+    fn test_tile_data_address_signed() {
+        let mut control = Control::new();
+        control.write(0);
+
+        assert_eq!(control.tile_data_address(0), 0x1000);
+        assert_eq!(control.tile_data_address(0x7F), 0x17F0);
+        assert_eq!(control.tile_data_address(0x80), 0x0800);
+        assert_eq!(control.tile_data_address(0xFF), 0x0FF0);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let mut control = Control::new();
+        control.write(0xFF);
+
+        assert_eq!(control.read(), 0xFF);
+    }
+}
@@ -1,12 +1,23 @@
-use crate::gameboy::interrupts::InterruptHandler;
+use crate::{emu::display::VIDEO_HEIGHT, gameboy::interrupts::InterruptHandler};
 
-use self::{control::Control, status::Status};
+use self::{
+    control::Control,
+    status::{Status, OAM_SCAN_CYCLES, TRANSFER_CYCLES},
+};
 
 mod control;
 mod status;
 
+const LINE_CYCLES: u8 = 114;
+const FIRST_LINE_SKIPPED_CYCLES: u8 = 1;
+
 pub(crate) struct Lcd {
     ly_cycle_counter: u8,
+    stat_line: bool,
+    stat_write_interrupt: bool,
+    first_line: bool,
+    first_frame: bool,
+    cgb: bool,
     status: Status,
     control: Control,
 }
@@ -16,10 +27,58 @@ impl Lcd {
         self.control.tile_map_area_address()
     }
 
+    pub fn tile_data_address(&self, tile: u8) -> u16 {
+        self.control.tile_data_address(tile)
+    }
+
+    pub fn is_bg_window_enabled(&self) -> bool {
+        self.control.is_bg_window_enabled()
+    }
+
+    pub fn window_tile_map_area_address(&self) -> u16 {
+        self.control.window_tile_map_area_address()
+    }
+
+    pub fn is_window_enabled(&self) -> bool {
+        self.control.is_window_enabled()
+    }
+
+    pub fn is_obj_enabled(&self) -> bool {
+        self.control.is_obj_enabled()
+    }
+
+    pub fn obj_height(&self) -> u8 {
+        self.control.obj_height()
+    }
+
+    pub fn is_transfer_start(&self) -> bool {
+        self.is_lcd_enabled()
+            && !self.first_frame
+            && self.status.ly < VIDEO_HEIGHT
+            && self.ly_cycle_counter == OAM_SCAN_CYCLES
+    }
+
+    // the cycle a visible line switches from mode 3 to mode 0
+    pub fn is_hblank_start(&self) -> bool {
+        self.is_lcd_enabled()
+            && self.status.ly < VIDEO_HEIGHT
+            && self.ly_cycle_counter == OAM_SCAN_CYCLES + TRANSFER_CYCLES
+    }
+
+    // the cycle ly reaches 144, when the whole frame has been drawn
+    pub fn is_vblank_start(&self) -> bool {
+        self.is_lcd_enabled() && self.status.ly == VIDEO_HEIGHT && self.ly_cycle_counter == 0
+    }
+
     pub fn is_lcd_enabled(&self) -> bool {
         self.control.is_lcd_enabled()
     }
 
+    // the first frame after the lcd is enabled isn't shown
+    pub fn is_first_frame(&self) -> bool {
+        self.first_frame
+    }
+
     fn is_mode_vblank(&self) -> bool {
         self.status.is_mode_vblank()
     }
@@ -33,13 +92,44 @@ impl Lcd {
     }
 
     pub fn write_control(&mut self, control: u8) {
+        let was_enabled = self.is_lcd_enabled();
         self.control.write(control);
-        if !self.is_lcd_enabled() {
-            self.status.reset_ly();
+        match (was_enabled, self.is_lcd_enabled()) {
+            (true, false) => self.disable(),
+            (false, true) => self.enable(),
+            _ => (),
         }
     }
 
+    fn disable(&mut self) {
+        self.ly_cycle_counter = 0;
+        self.stat_line = false;
+        self.stat_write_interrupt = false;
+        self.status.reset();
+    }
+
+    // line 0 restarts slightly late and without its oam scan
+    fn enable(&mut self) {
+        self.ly_cycle_counter = FIRST_LINE_SKIPPED_CYCLES;
+        self.first_line = true;
+        self.first_frame = true;
+        self.status.reset();
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    // the dmg briefly sees every stat source enabled on a write, the cgb doesn't
     pub fn write_stat(&mut self, stat: u8) {
+        if !self.cgb
+            && self.is_lcd_enabled()
+            && self.status.write_interrupt_line()
+            && !self.stat_line
+        {
+            self.stat_line = true;
+            self.stat_write_interrupt = true;
+        }
         self.status.write_stat(stat);
     }
 
@@ -48,10 +138,11 @@ impl Lcd {
     }
 
     fn inc_ly(&mut self) {
-        if !self.is_lcd_enabled() {
-            panic!("gpu inc_ly should not be called while lcd is not enable");
-        }
         self.status.inc_ly();
+        self.first_line = false;
+        if self.status.ly == 0 {
+            self.first_frame = false;
+        }
     }
 
     pub fn read_lyc(&self) -> u8 {
@@ -65,16 +156,31 @@ impl Lcd {
     pub fn update(&mut self, interrupt_handler: &mut InterruptHandler) {
         if self.is_lcd_enabled() {
             self.ly_cycle_counter += 1;
-            if self.ly_cycle_counter == 114 {
+            if self.ly_cycle_counter == LINE_CYCLES {
                 self.ly_cycle_counter = 0;
                 self.inc_ly();
-                if self.is_mode_vblank() {
-                    interrupt_handler.set_vblank_flag();
-                }
             }
-        } else {
-            self.ly_cycle_counter = 0;
+            self.update_mode(interrupt_handler);
+            self.update_stat_interrupt(interrupt_handler);
+        }
+    }
+
+    fn update_mode(&mut self, interrupt_handler: &mut InterruptHandler) {
+        let was_vblank = self.is_mode_vblank();
+        self.status
+            .update_mode(self.ly_cycle_counter, self.first_line);
+        if self.is_mode_vblank() && !was_vblank {
+            interrupt_handler.set_vblank_flag();
+        }
+    }
+
+    fn update_stat_interrupt(&mut self, interrupt_handler: &mut InterruptHandler) {
+        let stat_line = self.status.interrupt_line();
+        if (stat_line && !self.stat_line) || self.stat_write_interrupt {
+            interrupt_handler.set_lcd_stat_flag();
         }
+        self.stat_line = stat_line;
+        self.stat_write_interrupt = false;
     }
 
     pub fn new() -> Self {
@@ -83,8 +189,248 @@ impl Lcd {
         let control = Control::new();
         Self {
             ly_cycle_counter,
+            stat_line: false,
+            stat_write_interrupt: false,
+            first_line: false,
+            first_frame: false,
+            cgb: false,
             status,
             control,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::interrupts::{INTERRUPTS_LCD_STAT_BIT, INTERRUPTS_VBLANK_BIT};
+
+    // enables the lcd and runs past the first frame so tests start on a normal line 0
+    fn new_enabled_lcd() -> Lcd {
+        let mut lcd = Lcd::new();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_control(0x80);
+        while lcd.is_first_frame() {
+            lcd.update(interrupt_handler);
+        }
+        lcd
+    }
+
+    fn run(lcd: &mut Lcd, interrupt_handler: &mut InterruptHandler, cycles: u32) {
+        for _ in 0..cycles {
+            lcd.update(interrupt_handler);
+        }
+    }
+
+    fn stat_requested(interrupt_handler: &mut InterruptHandler) -> bool {
+        let requested = interrupt_handler.read_flags() & INTERRUPTS_LCD_STAT_BIT != 0;
+        interrupt_handler.write_flags(0);
+        requested
+    }
+
+    #[test]
+    fn test_update_modes() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+
+        run(&mut lcd, interrupt_handler, 19);
+        assert_eq!(lcd.read_stat() & 0b11, 2);
+        run(&mut lcd, interrupt_handler, 1);
+        assert_eq!(lcd.read_stat() & 0b11, 3);
+        run(&mut lcd, interrupt_handler, 43);
+        assert_eq!(lcd.read_stat() & 0b11, 0);
+        run(&mut lcd, interrupt_handler, 51);
+        assert_eq!(lcd.read_ly(), 1);
+        assert_eq!(lcd.read_stat() & 0b11, 2);
+    }
+
+    #[test]
+    fn test_hblank_start_once_per_visible_line() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+
+        let mut hblanks = 0;
+        for _ in 0..114 * 154 {
+            lcd.update(interrupt_handler);
+            if lcd.is_hblank_start() {
+                assert_eq!(lcd.read_stat() & 0b11, 0);
+                hblanks += 1;
+            }
+        }
+
+        assert_eq!(hblanks, 144);
+    }
+
+    #[test]
+    fn test_vblank_start_once_per_frame() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+
+        let mut vblanks = Vec::new();
+        for cycle in 1..=114 * 154 * 2 {
+            lcd.update(interrupt_handler);
+            if lcd.is_vblank_start() {
+                vblanks.push(cycle);
+            }
+        }
+
+        assert_eq!(vblanks, [114 * 144, 114 * 144 + 114 * 154]);
+    }
+
+    #[test]
+    fn test_update_vblank() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+
+        run(&mut lcd, interrupt_handler, 114 * 144 - 1);
+        assert_eq!(interrupt_handler.read_flags() & INTERRUPTS_VBLANK_BIT, 0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert_eq!(lcd.read_ly(), 144);
+        assert_eq!(lcd.read_stat() & 0b11, 1);
+        assert_ne!(interrupt_handler.read_flags() & INTERRUPTS_VBLANK_BIT, 0);
+
+        interrupt_handler.write_flags(0);
+        run(&mut lcd, interrupt_handler, 114 * 9);
+        assert_eq!(interrupt_handler.read_flags() & INTERRUPTS_VBLANK_BIT, 0);
+        run(&mut lcd, interrupt_handler, 114);
+        assert_eq!(lcd.read_ly(), 0);
+    }
+
+    #[test]
+    fn test_update_lyc() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_lyc(2);
+        lcd.write_stat(1 << 6);
+        stat_requested(interrupt_handler);
+
+        run(&mut lcd, interrupt_handler, 114 * 2 - 1);
+        assert_eq!(lcd.read_stat() & (1 << 2), 0);
+        assert!(!stat_requested(interrupt_handler));
+        run(&mut lcd, interrupt_handler, 1);
+        assert_ne!(lcd.read_stat() & (1 << 2), 0);
+        assert!(stat_requested(interrupt_handler));
+        run(&mut lcd, interrupt_handler, 113);
+        assert!(!stat_requested(interrupt_handler));
+    }
+
+    #[test]
+    fn test_update_stat_blocking() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_stat(1 << 5 | 1 << 3);
+
+        run(&mut lcd, interrupt_handler, 63);
+        assert!(stat_requested(interrupt_handler));
+        run(&mut lcd, interrupt_handler, 51);
+        assert_eq!(lcd.read_stat() & 0b11, 2);
+        assert!(!stat_requested(interrupt_handler));
+    }
+
+    #[test]
+    fn test_write_stat_spurious_interrupt() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_lyc(0xFF);
+
+        run(&mut lcd, interrupt_handler, 20);
+        lcd.write_stat(0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert!(!stat_requested(interrupt_handler));
+
+        run(&mut lcd, interrupt_handler, 43);
+        lcd.write_stat(0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert!(stat_requested(interrupt_handler));
+    }
+
+    #[test]
+    fn test_write_stat_no_spurious_interrupt_on_cgb() {
+        let mut lcd = new_enabled_lcd();
+        lcd.set_cgb_mode(true);
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_lyc(0xFF);
+
+        run(&mut lcd, interrupt_handler, 64);
+        lcd.write_stat(0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert!(!stat_requested(interrupt_handler));
+
+        run(&mut lcd, interrupt_handler, 114 * 144);
+        assert_eq!(lcd.read_stat() & 0b11, 1);
+        lcd.write_stat(0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert!(!stat_requested(interrupt_handler));
+    }
+
+    #[test]
+    fn test_enable_first_line() {
+        let mut lcd = Lcd::new();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_control(0x80);
+
+        assert_eq!(lcd.read_ly(), 0);
+        assert_eq!(lcd.read_stat() & 0b11, 0);
+        assert!(lcd.is_first_frame());
+        run(&mut lcd, interrupt_handler, 18);
+        assert_eq!(lcd.read_stat() & 0b11, 0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert_eq!(lcd.read_stat() & 0b11, 3);
+        run(&mut lcd, interrupt_handler, 93);
+        assert_eq!(lcd.read_ly(), 0);
+        run(&mut lcd, interrupt_handler, 1);
+        assert_eq!(lcd.read_ly(), 1);
+        assert_eq!(lcd.read_stat() & 0b11, 2);
+    }
+
+    #[test]
+    fn test_enable_first_frame() {
+        let mut lcd = Lcd::new();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_control(0x80);
+
+        run(&mut lcd, interrupt_handler, 114 * 154 - 2);
+        assert!(lcd.is_first_frame());
+        assert!(!lcd.is_transfer_start());
+        run(&mut lcd, interrupt_handler, 1);
+        assert!(!lcd.is_first_frame());
+        assert_eq!(lcd.read_ly(), 0);
+        run(&mut lcd, interrupt_handler, 20);
+        assert!(lcd.is_transfer_start());
+    }
+
+    #[test]
+    fn test_disable_mid_frame() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+        lcd.write_stat(1 << 5 | 1 << 3);
+        run(&mut lcd, interrupt_handler, 114 * 50 + 30);
+        interrupt_handler.write_flags(0);
+
+        lcd.write_control(0);
+        assert_eq!(lcd.read_ly(), 0);
+        assert_eq!(lcd.read_stat() & 0b11, 0);
+
+        run(&mut lcd, interrupt_handler, 114 * 154);
+        assert_eq!(lcd.read_ly(), 0);
+        assert_eq!(lcd.read_stat() & 0b11, 0);
+        assert_eq!(interrupt_handler.read_flags() & 0b11, 0);
+        assert!(!lcd.is_transfer_start());
+    }
+
+    #[test]
+    fn test_reenable_mid_frame() {
+        let mut lcd = new_enabled_lcd();
+        let interrupt_handler = &mut InterruptHandler::new();
+        run(&mut lcd, interrupt_handler, 114 * 80 + 50);
+
+        lcd.write_control(0);
+        run(&mut lcd, interrupt_handler, 500);
+        lcd.write_control(0x80);
+
+        assert_eq!(lcd.read_ly(), 0);
+        assert!(lcd.is_first_frame());
+        run(&mut lcd, interrupt_handler, 113);
+        assert_eq!(lcd.read_ly(), 1);
+    }
+}
@@ -1,10 +1,23 @@
+use crate::emu::display::VIDEO_HEIGHT;
+
+const LINES_PER_FRAME: u8 = 154;
+pub const OAM_SCAN_CYCLES: u8 = 20;
+pub const TRANSFER_CYCLES: u8 = 43;
+
+const STAT_UNUSED_BIT: u8 = 1 << 7;
+const STAT_LYC_INTERRUPT_BIT: u8 = 1 << 6;
+const STAT_OAM_INTERRUPT_BIT: u8 = 1 << 5;
+const STAT_VBLANK_INTERRUPT_BIT: u8 = 1 << 4;
+const STAT_HBLANK_INTERRUPT_BIT: u8 = 1 << 3;
+const STAT_COINCIDENCE_BIT: u8 = 1 << 2;
+
 pub(crate) struct Status {
     pub(crate) ly: u8,
     pub(crate) lyc: u8,
     pub(crate) stat: Stat,
 }
 
-#[derive(Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) enum Mode {
     Hblank,
     Vblank,
@@ -14,11 +27,55 @@ pub(crate) enum Mode {
 
 pub(crate) struct Stat {
     pub(crate) mode: Mode,
+    lyc_interrupt: bool,
+    oam_interrupt: bool,
+    vblank_interrupt: bool,
+    hblank_interrupt: bool,
 }
 
 impl Stat {
     fn new() -> Self {
-        Self { mode: Mode::Oam }
+        Self {
+            mode: Mode::Hblank,
+            lyc_interrupt: false,
+            oam_interrupt: false,
+            vblank_interrupt: false,
+            hblank_interrupt: false,
+        }
+    }
+
+    fn read(&self) -> u8 {
+        let mode = match self.mode {
+            Mode::Hblank => 0,
+            Mode::Vblank => 1,
+            Mode::Oam => 2,
+            Mode::Transfer => 3,
+        };
+
+        (if self.lyc_interrupt {
+            STAT_LYC_INTERRUPT_BIT
+        } else {
+            0
+        }) | (if self.oam_interrupt {
+            STAT_OAM_INTERRUPT_BIT
+        } else {
+            0
+        }) | (if self.vblank_interrupt {
+            STAT_VBLANK_INTERRUPT_BIT
+        } else {
+            0
+        }) | (if self.hblank_interrupt {
+            STAT_HBLANK_INTERRUPT_BIT
+        } else {
+            0
+        }) | mode
+    }
+
+    fn write(&mut self, byte: u8) {
+        self.lyc_interrupt = byte & STAT_LYC_INTERRUPT_BIT != 0;
+        self.oam_interrupt = byte & STAT_OAM_INTERRUPT_BIT != 0;
+        self.vblank_interrupt = byte & STAT_VBLANK_INTERRUPT_BIT != 0;
+        self.hblank_interrupt = byte & STAT_HBLANK_INTERRUPT_BIT != 0;
     }
 }
 
@@ -27,44 +84,73 @@ impl Status {
         self.stat.mode == Mode::Vblank
     }
 
+    pub fn is_coincidence(&self) -> bool {
+        self.ly == self.lyc
+    }
+
     pub fn inc_ly(&mut self) {
         self.ly += 1;
-        if self.ly == 155 {
+        if self.ly == LINES_PER_FRAME {
             self.ly = 0;
         }
+    }
 
-        if self.ly == 0 {
-            self.stat.mode = Mode::Oam;
-        }
-
-        if self.ly == 21 {
-            self.stat.mode = Mode::Hblank;
-        }
-
-        if self.ly == 145 {
-            self.stat.mode = Mode::Vblank;
-        }
+    // the first line after the lcd is enabled skips the oam scan and
+    // reports hblank until the transfer starts
+    pub fn update_mode(&mut self, cycle: u8, first_line: bool) {
+        self.stat.mode = if self.ly >= VIDEO_HEIGHT {
+            Mode::Vblank
+        } else if cycle < OAM_SCAN_CYCLES && first_line {
+            Mode::Hblank
+        } else if cycle < OAM_SCAN_CYCLES {
+            Mode::Oam
+        } else if cycle < OAM_SCAN_CYCLES + TRANSFER_CYCLES {
+            Mode::Transfer
+        } else {
+            Mode::Hblank
+        };
     }
 
-    pub fn reset_ly(&mut self) {
+    pub fn reset(&mut self) {
         self.ly = 0;
+        self.stat.mode = Mode::Hblank;
     }
 
     pub fn write_lyc(&mut self, lyc: u8) {
         self.lyc = lyc;
     }
 
+    // all enabled sources are or'd into a single line, the interrupt
+    // only fires when that line goes from low to high
+    pub fn interrupt_line(&self) -> bool {
+        let stat = &self.stat;
+        (stat.lyc_interrupt && self.is_coincidence())
+            || match stat.mode {
+                Mode::Hblank => stat.hblank_interrupt,
+                Mode::Vblank => stat.vblank_interrupt,
+                Mode::Oam => stat.oam_interrupt,
+                Mode::Transfer => false,
+            }
+    }
+
+    // on dmg writing stat briefly enables every source, which can raise an
+    // interrupt during hblank, vblank or on a lyc match
+    pub fn write_interrupt_line(&self) -> bool {
+        matches!(self.stat.mode, Mode::Hblank | Mode::Vblank) || self.is_coincidence()
+    }
+
     pub fn read_stat(&self) -> u8 {
-        let stat = match self.stat.mode {
-            Mode::Hblank => 0,
-            Mode::Vblank => 1,
-            Mode::Oam => 2,
-            Mode::Transfer => 3,
+        let coincidence = if self.is_coincidence() {
+            STAT_COINCIDENCE_BIT
+        } else {
+            0
         };
-        stat | 0b1_0000000
+        self.stat.read() | coincidence | STAT_UNUSED_BIT
     }
 
-    pub fn write_stat(&mut self, _: u8) {}
+    pub fn write_stat(&mut self, stat: u8) {
+        self.stat.write(stat);
+    }
 
     pub fn new() -> Self {
         Self {
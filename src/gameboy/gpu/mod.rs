@@ -1,6 +1,20 @@
+mod attributes;
+mod compatibility;
 mod lcd;
 mod palette;
+mod palette_ram;
 mod ppu;
+mod sprite;
+mod state;
+mod tiles;
+mod window;
+
+pub use self::attributes::TileAttributes;
+pub use self::compatibility::{compatibility_scheme, manual_scheme};
+pub use self::palette::{ColorScheme, Shades};
+pub use self::palette_ram::rgb555_color;
+pub use self::sprite::Sprite;
+pub use self::state::{PpuState, TILE_DATA_TILES};
 
 use self::ppu::Ppu;
 use super::interrupts::InterruptHandler;
@@ -9,12 +23,14 @@ use crate::emu::display::Display;
 pub const VRAM_BEGIN: u16 = 0x8000;
 pub const VRAM_END: u16 = 0x9FFF;
 pub const VRAM_SIZE: u16 = VRAM_END - VRAM_BEGIN + 1;
+pub const VRAM_BANKS: usize = 2;
 pub const OAM_BEGIN: u16 = 0xFE00;
 pub const OAM_END: u16 = 0xFE9F;
 pub const OAM_SIZE: u16 = OAM_END - OAM_BEGIN + 1;
-const PPU_REGISTERS_ADDRESSES: [u16; 9] = [
-    0xFF40, 0xFF41, 0xFF42, 0xFF43, 0xFF44, 0xFF45, 0xFF47, 0xFF48, 0xFF49,
+const PPU_REGISTERS_ADDRESSES: [u16; 11] = [
+    0xFF40, 0xFF41, 0xFF42, 0xFF43, 0xFF44, 0xFF45, 0xFF47, 0xFF48, 0xFF49, 0xFF4A, 0xFF4B,
 ];
+const CGB_REGISTERS_ADDRESSES: [u16; 5] = [0xFF4F, 0xFF68, 0xFF69, 0xFF6A, 0xFF6B];
 
 pub trait Gpu {
     fn write_vram(&mut self, address: u16, byte: u8);
@@ -23,8 +39,16 @@ pub trait Gpu {
     fn read_vram(&self, address: u16) -> u8;
     fn read_oam(&self, address: u16) -> u8;
     fn read_register(&self, address: u16) -> u8;
-    fn render_display(&mut self, display: &mut Box<dyn Display>);
+    fn render_display(&mut self, display: &mut dyn Display);
+    fn set_color_scheme(&mut self, scheme: ColorScheme);
+    fn set_cgb_mode(&mut self, cgb: bool);
+    fn state(&self) -> PpuState;
     fn update(&mut self, interrupt_handler: &mut InterruptHandler);
+    fn is_hblank_start(&self) -> bool;
+    fn is_lcd_enabled(&self) -> bool;
+    fn is_vblank_start(&self) -> bool;
+    fn shades(&self) -> Option<&[u8]>;
+    fn vram_transfer(&self) -> Vec<u8>;
     fn handles(&self, address: u16) -> bool;
 }
 
@@ -1,9 +1,10 @@
-use sdl2::pixels::Color;
+use crate::gameboy::Color;
 
 pub struct Palettes {
     pub background: Palette,
     pub object0: Palette,
     pub object1: Palette,
+    pub scheme: ColorScheme,
 }
 
 impl Palettes {
@@ -11,78 +12,160 @@ impl Palettes {
         let background = Palette::from(0);
         let object0 = Palette::from(0);
         let object1 = Palette::from(0);
+        let scheme = ColorScheme::grey();
 
         Self {
             background,
             object0,
             object1,
+            scheme,
         }
     }
+
+    pub fn background_color(&self, index: u8) -> Color {
+        self.scheme.background.color(self.background.shade(index))
+    }
+
+    pub fn object_shade(&self, palette1: bool, index: u8) -> u8 {
+        if palette1 {
+            self.object1.shade(index)
+        } else {
+            self.object0.shade(index)
+        }
+    }
+
+    pub fn object_color(&self, palette1: bool, index: u8) -> Color {
+        let shade = self.object_shade(palette1, index);
+        if palette1 {
+            self.scheme.object1.color(shade)
+        } else {
+            self.scheme.object0.color(shade)
+        }
+    }
+
+    // what the lcd shows with the bg disabled or the screen off
+    pub fn blank_color(&self) -> Color {
+        self.scheme.background.color(0)
+    }
 }
 
+// maps a dmg palette register's 2 bit color indexes to shades 0 (lightest) - 3 (darkest)
 pub struct Palette {
-    pub zero: Color,
-    one: Color,
-    two: Color,
-    three: Color,
+    shades: [u8; 4],
     pub byte: u8,
 }
 
 impl std::convert::From<u8> for Palette {
     fn from(byte: u8) -> Self {
         Palette {
-            zero: Palette::map_color(byte >> 1 & 1, byte & 1),
-            one: Palette::map_color(byte >> 3 & 1, byte >> 2 & 1),
-            two: Palette::map_color(byte >> 5 & 1, byte >> 4 & 1),
-            three: Palette::map_color(byte >> 7 & 1, byte >> 6 & 1),
+            shades: [
+                byte & 0b11,
+                byte >> 2 & 0b11,
+                byte >> 4 & 0b11,
+                byte >> 6 & 0b11,
+            ],
             byte,
         }
     }
 }
 
 impl Palette {
-    pub fn bytes_to_color(&self, upper: u8, lower: u8) -> Vec<Color> {
+    pub fn shade(&self, index: u8) -> u8 {
+        self.shades[index as usize]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shades {
+    colors: [Color; 4],
+}
+
+impl Shades {
+    pub fn new(colors: [Color; 4]) -> Self {
+        Self { colors }
+    }
+
+    fn rgb(colors: [u32; 4]) -> Self {
+        Self::new(colors.map(|rgb| Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)))
+    }
+
+    pub fn color(&self, shade: u8) -> Color {
+        self.colors[shade as usize]
+    }
+}
+
+// the colors used for each of the dmg's 4 shades, separately for bg/window and both object palettes
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorScheme {
+    pub name: String,
+    pub background: Shades,
+    pub object0: Shades,
+    pub object1: Shades,
+}
+
+impl ColorScheme {
+    pub fn new(name: &str, background: Shades, object0: Shades, object1: Shades) -> Self {
+        Self {
+            name: name.to_string(),
+            background,
+            object0,
+            object1,
+        }
+    }
+
+    pub fn uniform(name: &str, shades: Shades) -> Self {
+        Self::new(name, shades.clone(), shades.clone(), shades)
+    }
+
+    pub fn grey() -> Self {
+        Self::uniform(
+            "grey",
+            Shades::rgb([0xFFFFFF, 0xA9A9A9, 0x545454, 0x000000]),
+        )
+    }
+
+    pub fn presets() -> Vec<ColorScheme> {
         vec![
-            self.decode_color((upper >> 7) & 1, (lower >> 7) & 1),
-            self.decode_color((upper >> 6) & 1, (lower >> 6) & 1),
-            self.decode_color((upper >> 5) & 1, (lower >> 5) & 1),
-            self.decode_color((upper >> 4) & 1, (lower >> 4) & 1),
-            self.decode_color((upper >> 3) & 1, (lower >> 3) & 1),
-            self.decode_color((upper >> 2) & 1, (lower >> 2) & 1),
-            self.decode_color((upper >> 1) & 1, (lower >> 1) & 1),
-            self.decode_color(upper & 1, lower & 1),
+            Self::grey(),
+            Self::uniform("dmg", Shades::rgb([0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F])),
+            Self::uniform(
+                "pocket",
+                Shades::rgb([0xC4CFA1, 0x8B956D, 0x4D533C, 0x1F1F1F]),
+            ),
+            Self::uniform(
+                "light",
+                Shades::rgb([0x00B581, 0x009A71, 0x00694A, 0x004F3B]),
+            ),
+            Self::uniform(
+                "high-contrast",
+                Shades::rgb([0xFFFFFF, 0xC0C0C0, 0x404040, 0x000000]),
+            ),
         ]
     }
+}
 
-    fn decode_color(&self, upper: u8, lower: u8) -> Color {
-        match upper {
-            1 => match lower {
-                1 => self.three,
-                0 => self.two,
-                _ => panic!("invalid color"),
-            },
-            0 => match lower {
-                0 => self.zero,
-                1 => self.one,
-                _ => panic!("invalid color"),
-            },
-            _ => panic!("invalid color"),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_shades() {
+        let palette = Palette::from(0b11_10_01_00);
+
+        assert_eq!(palette.shade(0), 0);
+        assert_eq!(palette.shade(1), 1);
+        assert_eq!(palette.shade(2), 2);
+        assert_eq!(palette.shade(3), 3);
     }
 
-    fn map_color(upper: u8, lower: u8) -> Color {
-        match upper {
-            1 => match lower {
-                1 => Color::BLACK,
-                0 => Color::RGB(84, 84, 84),
-                _ => panic!("invalid color"),
-            },
-            0 => match lower {
-                0 => Color::WHITE,
-                1 => Color::RGB(169, 169, 169),
-                _ => panic!("invalid color"),
-            },
-            _ => panic!("invalid color"),
-        }
+    #[test]
+    fn test_palettes_object_colors() {
+        let mut palettes = Palettes::new();
+        palettes.scheme.object1 = Shades::rgb([0x000001, 0x000002, 0x000003, 0x000004]);
+        palettes.object0 = Palette::from(0b00_00_00_11);
+        palettes.object1 = Palette::from(0b00_00_00_11);
+
+        assert_eq!(palettes.object_color(false, 0), Color::BLACK);
+        assert_eq!(palettes.object_color(true, 0), Color::rgb(0, 0, 4));
     }
 }
@@ -0,0 +1,121 @@
+use crate::gameboy::Color;
+
+pub const BCPS_ADDRESS: u16 = 0xFF68;
+pub const BCPD_ADDRESS: u16 = 0xFF69;
+pub const OCPS_ADDRESS: u16 = 0xFF6A;
+pub const OCPD_ADDRESS: u16 = 0xFF6B;
+
+const PALETTE_RAM_SIZE: usize = 64;
+const INDEX_MASK: u8 = 0b0011_1111;
+const AUTO_INCREMENT_BIT: u8 = 1 << 7;
+
+// cgb palette memory, 8 palettes of 4 little endian rgb555 colors accessed through
+// a specification register (BCPS/OCPS) holding the index and a data register (BCPD/OCPD)
+pub(crate) struct PaletteRam {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl PaletteRam {
+    pub fn new() -> Self {
+        Self {
+            // without the cgb boot rom there is nothing to initialize the palettes so start white
+            data: [0xFF; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    pub fn read_spec(&self) -> u8 {
+        let auto_increment = if self.auto_increment {
+            AUTO_INCREMENT_BIT
+        } else {
+            0
+        };
+        auto_increment | 0b0100_0000 | self.index
+    }
+
+    pub fn write_spec(&mut self, byte: u8) {
+        self.index = byte & INDEX_MASK;
+        self.auto_increment = byte & AUTO_INCREMENT_BIT != 0;
+    }
+
+    // reading never increments the index, only writes do
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    pub fn write_data(&mut self, byte: u8) {
+        self.data[self.index as usize] = byte;
+        if self.auto_increment {
+            self.index = (self.index + 1) & INDEX_MASK;
+        }
+    }
+
+    pub fn color(&self, palette: u8, index: u8) -> Color {
+        let offset = palette as usize * 8 + index as usize * 2;
+        rgb555_color(u16::from_le_bytes([
+            self.data[offset],
+            self.data[offset + 1],
+        ]))
+    }
+}
+
+pub fn rgb555_color(rgb555: u16) -> Color {
+    Color::rgb(
+        scale_channel(rgb555),
+        scale_channel(rgb555 >> 5),
+        scale_channel(rgb555 >> 10),
+    )
+}
+
+// 5 bit channel to 8 bit, repeating the top bits so 0x1F maps to 0xFF
+fn scale_channel(rgb555: u16) -> u8 {
+    let channel = (rgb555 & 0x1F) as u8;
+    channel << 3 | channel >> 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_increment_wraps() {
+        let mut ram = PaletteRam::new();
+        ram.write_spec(AUTO_INCREMENT_BIT | 0x3F);
+
+        ram.write_data(0x12);
+        ram.write_data(0x34);
+
+        assert_eq!(ram.read_spec(), 0b1100_0001);
+        ram.write_spec(0x3F);
+        assert_eq!(ram.read_data(), 0x12);
+        ram.write_spec(0x00);
+        assert_eq!(ram.read_data(), 0x34);
+    }
+
+    #[test]
+    fn test_no_auto_increment() {
+        let mut ram = PaletteRam::new();
+        ram.write_spec(0x05);
+
+        ram.write_data(0x12);
+        ram.write_data(0x34);
+
+        assert_eq!(ram.read_spec(), 0b0100_0101);
+        assert_eq!(ram.read_data(), 0x34);
+    }
+
+    #[test]
+    fn test_rgb555_color() {
+        let mut ram = PaletteRam::new();
+        // palette 1 color 2, red 0x1F, green 0x10, blue 0x00
+        ram.write_spec(AUTO_INCREMENT_BIT | 12);
+        ram.write_data(0x1F);
+        ram.write_data(0x02);
+
+        assert_eq!(ram.color(1, 2), Color::rgb(0xFF, 0x84, 0x00));
+        assert_eq!(ram.color(0, 0), Color::rgb(0xFF, 0xFF, 0xFF));
+    }
+}
@@ -1,115 +1,251 @@
 use super::{
+    attributes::TileAttributes,
     lcd::Lcd,
-    palette::{Palette, Palettes},
-    Gpu, OAM_SIZE, PPU_REGISTERS_ADDRESSES, VRAM_SIZE,
+    palette::{ColorScheme, Palette, Palettes},
+    palette_ram::{PaletteRam, BCPD_ADDRESS, BCPS_ADDRESS, OCPD_ADDRESS, OCPS_ADDRESS},
+    sprite::{scan_oam, Sprite, SPRITE_X_OFFSET},
+    state::PpuState,
+    tiles::TileCache,
+    window::{Window, WX_ADDRESS, WY_ADDRESS},
+    Gpu, CGB_REGISTERS_ADDRESSES, OAM_SIZE, PPU_REGISTERS_ADDRESSES, VRAM_BANKS, VRAM_SIZE,
 };
-use crate::{emu::display::Display, gameboy::interrupts::InterruptHandler};
+use crate::{
+    emu::display::{Display, VIDEO_HEIGHT, VIDEO_WIDTH},
+    gameboy::{interrupts::InterruptHandler, Color},
+};
+
+const VBK_ADDRESS: u16 = 0xFF4F;
 
 pub(crate) struct Ppu {
-    vram: [u8; VRAM_SIZE as usize],
+    cgb: bool,
+    vram: [u8; VRAM_SIZE as usize * VRAM_BANKS],
+    vram_bank: usize,
     oam: [u8; OAM_SIZE as usize],
+    tiles: TileCache,
     palettes: Palettes,
+    background_palette_ram: PaletteRam,
+    object_palette_ram: PaletteRam,
     lcd: Lcd,
+    window: Window,
     scrollx: u8,
     scrolly: u8,
+    frame: Vec<Color>,
+    shades: Vec<u8>,
+    line: [u8; VIDEO_WIDTH as usize],
+    line_priority: [bool; VIDEO_WIDTH as usize],
 }
 
 impl Ppu {
     pub fn new() -> Box<dyn Gpu> {
         let lcd = Lcd::new();
+        let window = Window::new();
 
         Box::new(Self {
-            vram: [0; VRAM_SIZE as usize],
+            cgb: false,
+            vram: [0; VRAM_SIZE as usize * VRAM_BANKS],
+            vram_bank: 0,
             oam: [0; OAM_SIZE as usize],
+            tiles: TileCache::new(),
             palettes: Palettes::new(),
+            background_palette_ram: PaletteRam::new(),
+            object_palette_ram: PaletteRam::new(),
             lcd,
+            window,
             scrollx: 0,
             scrolly: 0,
+            frame: vec![Color::WHITE; VIDEO_WIDTH as usize * VIDEO_HEIGHT as usize],
+            shades: vec![0; VIDEO_WIDTH as usize * VIDEO_HEIGHT as usize],
+            line: [0; VIDEO_WIDTH as usize],
+            line_priority: [false; VIDEO_WIDTH as usize],
         })
     }
 
-    fn draw_tile_sprite(&mut self, x: u8, y: u8, tile_index: u16, display: &mut Box<dyn Display>) {
-        let mut row = 0;
-        let start = tile_index * 16;
-        let end = start + 16;
-        let x = x;
-        let y = y;
+    fn set_pixel(&mut self, x: u8, y: u8, color: Color) {
+        self.frame[y as usize * VIDEO_WIDTH as usize + x as usize] = color;
+    }
+
+    // dmg pixels also keep their shade for the sgb to colorize
+    fn set_dmg_pixel(&mut self, x: u8, y: u8, color: Color, shade: u8) {
+        self.shades[y as usize * VIDEO_WIDTH as usize + x as usize] = shade;
+        self.set_pixel(x, y, color);
+    }
+
+    fn vram_address(&self, address: u16) -> usize {
+        self.vram_bank * VRAM_SIZE as usize + address as usize
+    }
+
+    // the color indexes of row tile_y of the bg/window map entry at map_address, flipped as
+    // its attributes say
+    fn bg_tile_row(&mut self, map_address: u16, tile_y: u8) -> ([u8; 8], TileAttributes) {
+        let tile = self.vram[map_address as usize];
+        let attributes = if self.cgb {
+            TileAttributes::from(self.vram[VRAM_SIZE as usize + map_address as usize])
+        } else {
+            TileAttributes::default()
+        };
+        let row = if attributes.is_y_flipped() {
+            7 - tile_y
+        } else {
+            tile_y
+        };
 
-        for line in self.vram[(start as usize)..(end as usize)].chunks(2) {
-            let y = y + row;
-            if y < 16 || y >= 160 {
-                continue;
+        let tile_address = self.lcd.tile_data_address(tile);
+        let mut indexes = self
+            .tiles
+            .row(&self.vram, attributes.bank(), tile_address, row);
+        if attributes.is_x_flipped() {
+            indexes.reverse();
+        }
+        (indexes, attributes)
+    }
+
+    fn draw_bg_pixel(&mut self, x: u8, y: u8, index: u8, attributes: TileAttributes) {
+        self.line[x as usize] = index;
+        self.line_priority[x as usize] = attributes.has_priority();
+
+        if self.cgb {
+            let color = self
+                .background_palette_ram
+                .color(attributes.palette(), index);
+            self.set_pixel(x, y, color);
+        } else {
+            let color = self.palettes.background_color(index);
+            self.set_dmg_pixel(x, y, color, self.palettes.background.shade(index));
+        }
+    }
+
+    // draws line ly from x to the right edge out of row map_y of the 256x256 bg/window map,
+    // starting map_x pixels in. each tile's row is looked up once for the pixels it covers
+    fn draw_map_line(&mut self, ly: u8, map_area: u16, map_y: u8, mut x: u8, mut map_x: u8) {
+        let map_row = (map_y / 8) as u16 * 32;
+        while x < VIDEO_WIDTH {
+            let map_address = map_area + map_row + (map_x / 8) as u16;
+            let (row, attributes) = self.bg_tile_row(map_address, map_y % 8);
+            let tile_x = map_x % 8;
+            let span = (8 - tile_x).min(VIDEO_WIDTH - x);
+            for offset in 0..span {
+                self.draw_bg_pixel(x + offset, ly, row[(tile_x + offset) as usize], attributes);
             }
-            let y = y - 16;
-            let lower = line[0];
-            let upper = line[1];
-            let palette = &self.palettes.background;
-            let line_pixels = palette.bytes_to_color(upper, lower);
-            for (i, pixel) in line_pixels.iter().enumerate() {
-                let x = x + i as u8;
-                if x < 8 || x >= 168 || *pixel == palette.zero {
-                    continue;
-                }
-                let x = x - 8;
-                display.draw_pixel(x, y, *pixel);
+            x += span;
+            map_x = map_x.wrapping_add(span);
+        }
+    }
+
+    fn draw_line(&mut self) {
+        let ly = self.lcd.read_ly();
+        if ly == 0 {
+            self.window.reset();
+        }
+        self.window.check_y(ly);
+
+        // on cgb lcdc bit 0 doesn't hide the bg, it only takes away its priority over sprites
+        if self.cgb || self.lcd.is_bg_window_enabled() {
+            self.draw_line_bg(ly);
+            if self.lcd.is_window_enabled() {
+                self.draw_line_window(ly);
             }
-            row += 1;
+        } else {
+            self.clear_line_bg(ly);
+        }
+        self.draw_line_sprites(ly);
+    }
+
+    fn clear_line_bg(&mut self, ly: u8) {
+        self.line.fill(0);
+        self.line_priority.fill(false);
+        let color = self.palettes.blank_color();
+        for x in 0..VIDEO_WIDTH {
+            self.set_dmg_pixel(x, ly, color, 0);
+        }
+    }
+
+    // the bg map is 256x256 so scrolling wraps around on both axes
+    fn draw_line_bg(&mut self, ly: u8) {
+        let y = ly.wrapping_add(self.scrolly);
+        let map_area = self.lcd.tile_map_area_address();
+        self.draw_map_line(ly, map_area, y, 0, self.scrollx);
+    }
+
+    fn draw_line_window(&mut self, ly: u8) {
+        if !self.window.is_visible() {
+            return;
+        }
+
+        let start = self.window.start_x(self.scrollx);
+        let x = start.max(0);
+        let map_area = self.lcd.window_tile_map_area_address();
+        if x < VIDEO_WIDTH as i16 {
+            self.draw_map_line(ly, map_area, self.window.line(), x as u8, (x - start) as u8);
         }
+
+        self.window.next_line();
     }
 
-    fn draw_tile_bg(&mut self, x: u8, y: u8, tile_index: u16, display: &mut Box<dyn Display>) {
-        let mut row = 0;
-        let tile = self.vram[(tile_index + self.lcd.tile_map_area_address()) as usize];
-        let start = (tile as u16) * 16;
-        let end = start + 16;
+    fn draw_line_sprites(&mut self, ly: u8) {
+        if !self.lcd.is_obj_enabled() {
+            return;
+        }
+
+        let height = self.lcd.obj_height();
+        let mut drawn = [false; VIDEO_WIDTH as usize];
 
-        for line in self.vram[(start as usize)..(end as usize)].chunks(2) {
-            let mut y = y + row;
-            let scrolly_end = self.scrolly.wrapping_add(143);
-            if self.scrolly > scrolly_end {
-                if y < self.scrolly && y > (scrolly_end) {
-                    row += 1;
+        for sprite in scan_oam(&self.oam, ly, height, self.cgb) {
+            let tile_address = sprite.tile(height) as u16 * 16;
+            let bank = if self.cgb { sprite.bank() } else { 0 };
+            let row = self
+                .tiles
+                .row(&self.vram, bank, tile_address, sprite.row(ly, height));
+            for i in 0..8 {
+                let x = sprite.x as i16 + i - SPRITE_X_OFFSET;
+                if !(0..VIDEO_WIDTH as i16).contains(&x) || drawn[x as usize] {
                     continue;
                 }
-                if y >= self.scrolly {
-                    y = y - self.scrolly;
-                } else {
-                    y = y + (143 - scrolly_end);
-                }
-            } else {
-                if y < self.scrolly || y > (scrolly_end) {
-                    row += 1;
+                let pixel_x = if sprite.is_x_flipped() { 7 - i } else { i };
+                let index = row[pixel_x as usize];
+                if index == 0 {
                     continue;
                 }
-                y = y - self.scrolly;
-            }
-            let lower = line[0];
-            let upper = line[1];
-            let palette = &self.palettes.background;
-            let line_pixels = palette.bytes_to_color(upper, lower);
-            for (i, pixel) in line_pixels.iter().enumerate() {
-                let x = x + i as u8;
-                if x > 159 {
+                // the first opaque sprite pixel wins even when it ends up hidden behind bg
+                drawn[x as usize] = true;
+                if self.is_bg_over_sprite(x as usize, &sprite) {
                     continue;
                 }
-                display.draw_pixel(x, y, *pixel);
+                if self.cgb {
+                    let color = self.object_palette_ram.color(sprite.cgb_palette(), index);
+                    self.set_pixel(x as u8, ly, color);
+                } else {
+                    let palette1 = sprite.is_palette1();
+                    let color = self.palettes.object_color(palette1, index);
+                    let shade = self.palettes.object_shade(palette1, index);
+                    self.set_dmg_pixel(x as u8, ly, color, shade);
+                }
             }
-            row += 1;
         }
     }
+
+    // bg color 0 is always behind sprites. on cgb clearing lcdc bit 0 puts every
+    // sprite on top, otherwise either the sprite or bg map attribute can raise the bg
+    fn is_bg_over_sprite(&self, x: usize, sprite: &Sprite) -> bool {
+        if self.line[x] == 0 || (self.cgb && !self.lcd.is_bg_window_enabled()) {
+            return false;
+        }
+        sprite.is_behind_bg() || self.line_priority[x]
+    }
 }
 
 impl Gpu for Ppu {
     fn handles(&self, address: u16) -> bool {
         PPU_REGISTERS_ADDRESSES.contains(&address)
+            || (self.cgb && CGB_REGISTERS_ADDRESSES.contains(&address))
     }
 
     fn write_vram(&mut self, address: u16, byte: u8) {
-        self.vram[address as usize] = byte;
+        self.vram[self.vram_address(address)] = byte;
+        self.tiles.invalidate(self.vram_bank, address);
     }
 
     fn read_vram(&self, address: u16) -> u8 {
-        self.vram[address as usize]
+        self.vram[self.vram_address(address)]
     }
 
     fn write_oam(&mut self, address: u16, byte: u8) {
@@ -122,6 +258,13 @@ impl Gpu for Ppu {
 
     fn write_register(&mut self, address: u16, byte: u8) {
         match address {
+            OCPD_ADDRESS => self.object_palette_ram.write_data(byte),
+            OCPS_ADDRESS => self.object_palette_ram.write_spec(byte),
+            BCPD_ADDRESS => self.background_palette_ram.write_data(byte),
+            BCPS_ADDRESS => self.background_palette_ram.write_spec(byte),
+            VBK_ADDRESS => self.vram_bank = (byte & 1) as usize,
+            WX_ADDRESS => self.window.x = byte,
+            WY_ADDRESS => self.window.y = byte,
             0xFF49 => self.palettes.object1 = Palette::from(byte),
             0xFF48 => self.palettes.object0 = Palette::from(byte),
             0xFF47 => self.palettes.background = Palette::from(byte),
@@ -137,6 +280,13 @@ impl Gpu for Ppu {
 
     fn read_register(&self, address: u16) -> u8 {
         match address {
+            OCPD_ADDRESS => self.object_palette_ram.read_data(),
+            OCPS_ADDRESS => self.object_palette_ram.read_spec(),
+            BCPD_ADDRESS => self.background_palette_ram.read_data(),
+            BCPS_ADDRESS => self.background_palette_ram.read_spec(),
+            VBK_ADDRESS => 0b1111_1110 | self.vram_bank as u8,
+            WX_ADDRESS => self.window.x,
+            WY_ADDRESS => self.window.y,
             0xFF49 => self.palettes.object1.byte,
             0xFF48 => self.palettes.object0.byte,
             0xFF47 => self.palettes.background.byte,
@@ -150,31 +300,123 @@ impl Gpu for Ppu {
         }
     }
 
+    fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        self.palettes.scheme = scheme;
+    }
+
+    fn set_cgb_mode(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        self.lcd.set_cgb_mode(cgb);
+        self.vram_bank = 0;
+    }
+
+    fn state(&self) -> PpuState {
+        PpuState::new(
+            &self.vram,
+            self.cgb,
+            &self.oam,
+            self.lcd.read_control(),
+            self.scrollx,
+            self.scrolly,
+            &self.palettes,
+        )
+    }
+
     fn update(&mut self, interrupt_handler: &mut InterruptHandler) {
         self.lcd.update(interrupt_handler);
+        if self.lcd.is_transfer_start() {
+            self.draw_line();
+        }
+    }
+
+    fn is_hblank_start(&self) -> bool {
+        self.lcd.is_hblank_start()
+    }
+
+    fn is_vblank_start(&self) -> bool {
+        self.lcd.is_vblank_start()
+    }
+
+    fn is_lcd_enabled(&self) -> bool {
+        self.lcd.is_lcd_enabled()
+    }
+
+    fn shades(&self) -> Option<&[u8]> {
+        if !self.lcd.is_lcd_enabled() || self.lcd.is_first_frame() {
+            return None;
+        }
+        Some(&self.shades)
+    }
+
+    // the sgb reads vram transfers off the screen, 4KiB as 256 tiles laid out
+    // 20 to a row in bg map order from the top left
+    fn vram_transfer(&self) -> Vec<u8> {
+        let map = self.lcd.tile_map_area_address() as usize;
+        let mut data = Vec::with_capacity(0x1000);
+        for tile in 0..256 {
+            let tile_index = self.vram[map + (tile / 20) * 32 + tile % 20];
+            let address = self.lcd.tile_data_address(tile_index) as usize;
+            data.extend_from_slice(&self.vram[address..address + 16]);
+        }
+        data
     }
 
-    fn render_display(&mut self, display: &mut Box<dyn Display>) {
-        if !self.lcd.is_lcd_enabled() {
+    fn render_display(&mut self, display: &mut dyn Display) {
+        if !self.lcd.is_lcd_enabled() || self.lcd.is_first_frame() {
             display.off();
             return;
         }
 
-        for tile_index in 0u16..1024 {
-            let col = (tile_index) % 32;
-            let row = (tile_index) / 32;
-            self.draw_tile_bg(col as u8 * 8, row as u8 * 8, tile_index, display);
+        for y in 0..VIDEO_HEIGHT {
+            for x in 0..VIDEO_WIDTH {
+                let color = self.frame[y as usize * VIDEO_WIDTH as usize + x as usize];
+                display.draw_pixel(x, y, color);
+            }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: usize = 154;
+    const LINE_CYCLES: usize = 114;
+    const WINDOW_TILE: u8 = 0b0001_1011;
 
-        for sprite_index in 0..40 {
-            let y = self.oam[4 * sprite_index];
-            let x = self.oam[(4 * sprite_index) + 1];
-            let tile_index = self.oam[(4 * sprite_index) + 2];
-            let attributes = self.oam[(4 * sprite_index) + 3];
-            if y == 0 && x == 0 && tile_index == 0 && attributes == 0 {
-                continue;
+    #[test]
+    fn test_bg_and_window_line() {
+        let mut ppu = Ppu::new();
+        let mut interrupt_handler = InterruptHandler::new();
+        // each row of tile n is n's bits, so a pixel shows which tile and column it came from
+        for tile in 0..32 {
+            for row in 0..8 {
+                ppu.write_vram(tile * 16 + row * 2, tile as u8);
             }
-            self.draw_tile_sprite(x, y, tile_index as u16, display);
+            ppu.write_vram(0x1800 + tile, tile as u8);
+            ppu.write_vram(0x1C00 + tile, WINDOW_TILE);
         }
+        ppu.write_register(0xFF47, 0xE4);
+        ppu.write_register(0xFF43, 3);
+        ppu.write_register(WY_ADDRESS, 0);
+        ppu.write_register(WX_ADDRESS, 87);
+        ppu.write_register(0xFF40, 0xF1);
+
+        // the first frame after turning the lcd on isn't shown
+        for _ in 0..LINES * LINE_CYCLES * 2 {
+            ppu.update(&mut interrupt_handler);
+        }
+
+        let expected: Vec<u8> = (0..VIDEO_WIDTH as usize)
+            .map(|x| {
+                let (tile, column) = if x < 80 {
+                    ((x + 3) / 8, (x + 3) % 8)
+                } else {
+                    (WINDOW_TILE as usize, (x - 80) % 8)
+                };
+                (tile >> (7 - column)) as u8 & 1
+            })
+            .collect();
+        assert_eq!(ppu.shades().unwrap()[..VIDEO_WIDTH as usize], expected);
     }
 }
@@ -0,0 +1,183 @@
+const SPRITES_PER_LINE: usize = 10;
+pub const SPRITE_COUNT: usize = 40;
+const SPRITE_Y_OFFSET: u16 = 16;
+pub const SPRITE_X_OFFSET: i16 = 8;
+
+const ATTRIBUTE_CGB_PALETTE_MASK: u8 = 0b0000_0111;
+const ATTRIBUTE_BANK_BIT: u8 = 1 << 3;
+const ATTRIBUTE_PALETTE_BIT: u8 = 1 << 4;
+const ATTRIBUTE_X_FLIP_BIT: u8 = 1 << 5;
+const ATTRIBUTE_Y_FLIP_BIT: u8 = 1 << 6;
+const ATTRIBUTE_PRIORITY_BIT: u8 = 1 << 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub index: usize,
+}
+
+impl Sprite {
+    pub(crate) fn from_oam(oam: &[u8], index: usize) -> Self {
+        let base = index * 4;
+        Self {
+            y: oam[base],
+            x: oam[base + 1],
+            tile: oam[base + 2],
+            attributes: oam[base + 3],
+            index,
+        }
+    }
+
+    pub fn is_palette1(&self) -> bool {
+        self.attributes & ATTRIBUTE_PALETTE_BIT != 0
+    }
+
+    pub fn cgb_palette(&self) -> u8 {
+        self.attributes & ATTRIBUTE_CGB_PALETTE_MASK
+    }
+
+    pub fn bank(&self) -> usize {
+        (self.attributes & ATTRIBUTE_BANK_BIT != 0) as usize
+    }
+
+    pub fn is_x_flipped(&self) -> bool {
+        self.attributes & ATTRIBUTE_X_FLIP_BIT != 0
+    }
+
+    pub fn is_y_flipped(&self) -> bool {
+        self.attributes & ATTRIBUTE_Y_FLIP_BIT != 0
+    }
+
+    pub fn is_behind_bg(&self) -> bool {
+        self.attributes & ATTRIBUTE_PRIORITY_BIT != 0
+    }
+
+    // row of the sprite drawn on line ly, with y flip applied
+    pub fn row(&self, ly: u8, height: u8) -> u8 {
+        let row = (ly as u16 + SPRITE_Y_OFFSET - self.y as u16) as u8;
+        if self.is_y_flipped() {
+            height - 1 - row
+        } else {
+            row
+        }
+    }
+
+    // 8x16 sprites ignore the low bit of the tile index
+    pub fn tile(&self, height: u8) -> u8 {
+        if height == 16 {
+            self.tile & 0xFE
+        } else {
+            self.tile
+        }
+    }
+
+    fn is_on_line(&self, ly: u8, height: u8) -> bool {
+        let line = ly as u16 + SPRITE_Y_OFFSET;
+        let y = self.y as u16;
+        (y..y + height as u16).contains(&line)
+    }
+}
+
+// mode 2 oam scan, selects the first 10 sprites in oam order on the line and
+// returns them in drawing priority. on dmg that's lowest x first with ties going
+// to oam order, cgb only uses oam order
+pub fn scan_oam(oam: &[u8], ly: u8, height: u8, cgb: bool) -> Vec<Sprite> {
+    let mut sprites: Vec<Sprite> = (0..SPRITE_COUNT)
+        .map(|index| Sprite::from_oam(oam, index))
+        .filter(|sprite| sprite.is_on_line(ly, height))
+        .take(SPRITES_PER_LINE)
+        .collect();
+    if !cgb {
+        sprites.sort_by_key(|sprite| (sprite.x, sprite.index));
+    }
+    sprites
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oam_with(sprites: &[(u8, u8, u8, u8)]) -> [u8; 160] {
+        let mut oam = [0; 160];
+        for (index, (y, x, tile, attributes)) in sprites.iter().enumerate() {
+            oam[index * 4] = *y;
+            oam[index * 4 + 1] = *x;
+            oam[index * 4 + 2] = *tile;
+            oam[index * 4 + 3] = *attributes;
+        }
+        oam
+    }
+
+    #[test]
+    fn test_scan_oam_limit() {
+        let oam = oam_with(&[(16, 8, 0, 0); 12]);
+
+        let sprites = scan_oam(&oam, 0, 8, false);
+
+        assert_eq!(sprites.len(), 10);
+        assert_eq!(sprites.last().unwrap().index, 9);
+    }
+
+    #[test]
+    fn test_scan_oam_skips_other_lines() {
+        let oam = oam_with(&[(16, 8, 0, 0), (30, 8, 0, 0), (0, 8, 0, 0)]);
+
+        let sprites = scan_oam(&oam, 0, 8, false);
+
+        assert_eq!(sprites.len(), 1);
+        assert_eq!(sprites[0].index, 0);
+    }
+
+    #[test]
+    fn test_scan_oam_tall_sprites() {
+        let oam = oam_with(&[(16, 8, 0, 0)]);
+
+        assert!(scan_oam(&oam, 12, 8, false).is_empty());
+        assert_eq!(scan_oam(&oam, 12, 16, false).len(), 1);
+    }
+
+    #[test]
+    fn test_scan_oam_x_priority() {
+        let oam = oam_with(&[(16, 20, 0, 0), (16, 10, 0, 0), (16, 20, 0, 0)]);
+
+        let sprites = scan_oam(&oam, 0, 8, false);
+        let order: Vec<usize> = sprites.iter().map(|sprite| sprite.index).collect();
+
+        assert_eq!(order, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_scan_oam_cgb_priority() {
+        let oam = oam_with(&[(16, 20, 0, 0), (16, 10, 0, 0), (16, 20, 0, 0)]);
+
+        let sprites = scan_oam(&oam, 0, 8, true);
+        let order: Vec<usize> = sprites.iter().map(|sprite| sprite.index).collect();
+
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cgb_attributes() {
+        let oam = oam_with(&[(16, 8, 0, 0b0000_1110)]);
+        let sprite = Sprite::from_oam(&oam, 0);
+
+        assert_eq!(sprite.cgb_palette(), 6);
+        assert_eq!(sprite.bank(), 1);
+        assert!(!sprite.is_palette1());
+    }
+
+    #[test]
+    fn test_row_y_flip() {
+        let oam = oam_with(&[(16, 8, 0x11, ATTRIBUTE_Y_FLIP_BIT)]);
+        let sprite = Sprite::from_oam(&oam, 0);
+
+        assert_eq!(sprite.row(0, 8), 7);
+        assert_eq!(sprite.row(0, 16), 15);
+        assert_eq!(sprite.row(15, 16), 0);
+        assert_eq!(sprite.tile(16), 0x10);
+        assert_eq!(sprite.tile(8), 0x11);
+    }
+}
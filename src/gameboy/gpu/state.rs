@@ -0,0 +1,104 @@
+use crate::gameboy::Color;
+
+use super::{
+    attributes::TileAttributes,
+    palette::Palettes,
+    sprite::{Sprite, SPRITE_COUNT},
+    tiles::decode_row,
+    VRAM_SIZE,
+};
+
+pub const TILE_DATA_TILES: usize = 384;
+const TILE_MAP_ADDRESSES: [usize; 2] = [0x1800, 0x1C00];
+
+// read only copy of the ppu for debug viewers
+pub struct PpuState {
+    // both banks back to back, bank 1 is only used on cgb
+    pub vram: Vec<u8>,
+    pub cgb: bool,
+    pub oam: Vec<u8>,
+    pub lcdc: u8,
+    pub scrollx: u8,
+    pub scrolly: u8,
+    pub bgp: u8,
+    pub obp0: u8,
+    pub obp1: u8,
+    pub background_colors: [Color; 4],
+    pub object0_colors: [Color; 4],
+    pub object1_colors: [Color; 4],
+}
+
+impl PpuState {
+    pub(crate) fn new(
+        vram: &[u8],
+        cgb: bool,
+        oam: &[u8],
+        lcdc: u8,
+        scrollx: u8,
+        scrolly: u8,
+        palettes: &Palettes,
+    ) -> Self {
+        Self {
+            vram: vram.to_vec(),
+            cgb,
+            oam: oam.to_vec(),
+            lcdc,
+            scrollx,
+            scrolly,
+            bgp: palettes.background.byte,
+            obp0: palettes.object0.byte,
+            obp1: palettes.object1.byte,
+            background_colors: [0, 1, 2, 3].map(|index| palettes.background_color(index)),
+            object0_colors: [0, 1, 2, 3].map(|index| palettes.object_color(false, index)),
+            object1_colors: [0, 1, 2, 3].map(|index| palettes.object_color(true, index)),
+        }
+    }
+
+    // tile is the index into the whole tile data area of the bank, 0 - 383
+    pub fn tile_row(&self, bank: usize, tile: usize, row: u8) -> [u8; 8] {
+        let address = bank * VRAM_SIZE as usize + tile * 16 + row as usize * 2;
+        decode_row(self.vram[address + 1], self.vram[address])
+    }
+
+    // tile data index of a bg map entry using the current lcdc addressing mode
+    pub fn map_tile(&self, map: usize, x: usize, y: usize) -> usize {
+        let tile = self.vram[TILE_MAP_ADDRESSES[map] + y * 32 + x];
+        if self.lcdc & (1 << 4) != 0 {
+            tile as usize
+        } else {
+            (256 + tile as i8 as isize) as usize
+        }
+    }
+
+    // the cgb attributes of a bg map entry, kept in bank 1. the default on dmg
+    pub fn map_attributes(&self, map: usize, x: usize, y: usize) -> TileAttributes {
+        if self.cgb {
+            let address = VRAM_SIZE as usize + TILE_MAP_ADDRESSES[map] + y * 32 + x;
+            TileAttributes::from(self.vram[address])
+        } else {
+            TileAttributes::default()
+        }
+    }
+
+    pub fn active_bg_map(&self) -> usize {
+        if self.lcdc & (1 << 3) != 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn obj_height(&self) -> u8 {
+        if self.lcdc & (1 << 2) != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    pub fn sprites(&self) -> Vec<Sprite> {
+        (0..SPRITE_COUNT)
+            .map(|index| Sprite::from_oam(&self.oam, index))
+            .collect()
+    }
+}
@@ -0,0 +1,119 @@
+use super::{VRAM_BANKS, VRAM_SIZE};
+
+const TILE_COUNT: usize = 384;
+const TILE_SIZE: usize = 16;
+const TILE_DATA_END: u16 = 0x17FF;
+
+type Tile = [[u8; 8]; 8];
+
+// decoded 2bpp tiles from the vram tile data area of both banks, only tiles
+// written to since they were last decoded get decoded again
+pub(crate) struct TileCache {
+    tiles: Box<[Tile; TILE_COUNT * VRAM_BANKS]>,
+    dirty: [bool; TILE_COUNT * VRAM_BANKS],
+}
+
+impl TileCache {
+    pub fn new() -> Self {
+        Self {
+            tiles: Box::new([[[0; 8]; 8]; TILE_COUNT * VRAM_BANKS]),
+            dirty: [true; TILE_COUNT * VRAM_BANKS],
+        }
+    }
+
+    pub fn invalidate(&mut self, bank: usize, address: u16) {
+        if address <= TILE_DATA_END {
+            self.dirty[bank * TILE_COUNT + address as usize / TILE_SIZE] = true;
+        }
+    }
+
+    // color indexes of a row, rows past 7 continue into the next tile for 8x16 sprites.
+    // vram holds every bank back to back
+    pub fn row(&mut self, vram: &[u8], bank: usize, tile_address: u16, row: u8) -> [u8; 8] {
+        let tile = bank * TILE_COUNT + tile_address as usize / TILE_SIZE + row as usize / 8;
+        if self.dirty[tile] {
+            self.decode(vram, tile);
+        }
+        self.tiles[tile][row as usize % 8]
+    }
+
+    fn decode(&mut self, vram: &[u8], tile: usize) {
+        let bank = tile / TILE_COUNT;
+        let start = bank * VRAM_SIZE as usize + (tile % TILE_COUNT) * TILE_SIZE;
+        for (row, bytes) in vram[start..start + TILE_SIZE].chunks(2).enumerate() {
+            self.tiles[tile][row] = decode_row(bytes[1], bytes[0]);
+        }
+        self.dirty[tile] = false;
+    }
+}
+
+pub fn decode_row(upper: u8, lower: u8) -> [u8; 8] {
+    let mut row = [0; 8];
+    for (x, index) in row.iter_mut().enumerate() {
+        let bit = 7 - x;
+        *index = ((upper >> bit) & 1) << 1 | ((lower >> bit) & 1);
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_row() {
+        let row = decode_row(0b1100_0011, 0b1010_0101);
+
+        assert_eq!(row, [3, 2, 1, 0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_row_cached_until_invalidated() {
+        let mut vram = [0; 0x2000];
+        let mut cache = TileCache::new();
+        vram[0x10] = 0xFF;
+
+        assert_eq!(cache.row(&vram, 0, 0x10, 0), [1; 8]);
+
+        vram[0x10] = 0x00;
+        assert_eq!(cache.row(&vram, 0, 0x10, 0), [1; 8]);
+
+        cache.invalidate(0, 0x10);
+        assert_eq!(cache.row(&vram, 0, 0x10, 0), [0; 8]);
+    }
+
+    #[test]
+    fn test_row_tall_sprite() {
+        let mut vram = [0; 0x2000];
+        let mut cache = TileCache::new();
+        vram[0x12] = 0xFF;
+        vram[0x13] = 0xFF;
+
+        assert_eq!(cache.row(&vram, 0, 0x00, 9), [3; 8]);
+    }
+
+    #[test]
+    fn test_invalidate_ignores_tile_maps() {
+        let mut cache = TileCache::new();
+        let vram = [0; 0x2000];
+        cache.row(&vram, 0, 0x17F0, 0);
+
+        cache.invalidate(0, 0x1800);
+
+        assert!(!cache.dirty[TILE_COUNT - 1]);
+    }
+
+    #[test]
+    fn test_row_bank1() {
+        let mut vram = [0; 0x4000];
+        let mut cache = TileCache::new();
+        vram[0x2010] = 0xFF;
+
+        assert_eq!(cache.row(&vram, 0, 0x10, 0), [0; 8]);
+        assert_eq!(cache.row(&vram, 1, 0x10, 0), [1; 8]);
+
+        vram[0x2010] = 0x00;
+        cache.invalidate(1, 0x10);
+        assert_eq!(cache.row(&vram, 1, 0x10, 0), [0; 8]);
+    }
+}
@@ -0,0 +1,114 @@
+pub const WY_ADDRESS: u16 = 0xFF4A;
+pub const WX_ADDRESS: u16 = 0xFF4B;
+const WX_OFFSET: i16 = 7;
+const WX_MAX_VISIBLE: u8 = 166;
+
+pub(crate) struct Window {
+    pub(crate) x: u8, // WX
+    pub(crate) y: u8, // WY
+    line: u8,
+    triggered: bool,
+}
+
+impl Window {
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            line: 0,
+            triggered: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.line = 0;
+        self.triggered = false;
+    }
+
+    // the window only starts once LY == WY has been seen during the frame,
+    // moving WY afterwards doesn't hide it again until the next frame
+    pub fn check_y(&mut self, ly: u8) {
+        if ly == self.y {
+            self.triggered = true;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.triggered && self.x <= WX_MAX_VISIBLE
+    }
+
+    pub fn line(&self) -> u8 {
+        self.line
+    }
+
+    pub fn next_line(&mut self) {
+        self.line = self.line.wrapping_add(1);
+    }
+
+    // screen x of the windows first pixel, negative when WX < 7.
+    // on dmg WX=0 also drops the fine scroll pixels of SCX
+    pub fn start_x(&self, scrollx: u8) -> i16 {
+        let start = self.x as i16 - WX_OFFSET;
+        if self.x == 0 {
+            start - (scrollx & 7) as i16
+        } else {
+            start
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_visible_before_triggered() {
+        let mut window = Window::new();
+        window.y = 10;
+        window.check_y(9);
+
+        assert!(!window.is_visible());
+    }
+
+    #[test]
+    fn test_stays_visible_after_wy_changes() {
+        let mut window = Window::new();
+        window.y = 10;
+        window.check_y(10);
+        window.y = 100;
+        window.check_y(11);
+
+        assert!(window.is_visible());
+    }
+
+    #[test]
+    fn test_not_visible_wx_offscreen() {
+        let mut window = Window::new();
+        window.x = 167;
+        window.check_y(0);
+
+        assert!(!window.is_visible());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut window = Window::new();
+        window.check_y(0);
+        window.next_line();
+        window.reset();
+
+        assert_eq!(window.line(), 0);
+        assert!(!window.is_visible());
+    }
+
+    #[test]
+    fn test_start_x() {
+        let mut window = Window::new();
+        window.x = 7;
+        assert_eq!(window.start_x(3), 0);
+        window.x = 3;
+        assert_eq!(window.start_x(3), -4);
+        window.x = 0;
+        assert_eq!(window.start_x(3), -10);
+    }
+}
@@ -0,0 +1,246 @@
+use super::gpu::VRAM_BEGIN;
+use crate::gameboy::bus::Bus;
+
+pub const HDMA1_ADDRESS: u16 = 0xFF51;
+pub const HDMA2_ADDRESS: u16 = 0xFF52;
+pub const HDMA3_ADDRESS: u16 = 0xFF53;
+pub const HDMA4_ADDRESS: u16 = 0xFF54;
+pub const HDMA5_ADDRESS: u16 = 0xFF55;
+
+const BLOCK_SIZE: u16 = 0x10;
+const BYTES_PER_CYCLE: u16 = 2;
+const DESTINATION_MASK: u16 = 0x1FF0;
+const HBLANK_BIT: u8 = 1 << 7;
+const LENGTH_MASK: u8 = 0b0111_1111;
+
+#[derive(Eq, PartialEq, Debug)]
+enum Mode {
+    Off,
+    General,
+    Hblank,
+}
+
+// cgb vram dma. copies 0x10 byte blocks into vram either all at once (general purpose)
+// or one block per hblank, the cpu is stalled while a block is being copied
+pub struct Hdma {
+    source: u16,
+    destination: u16,
+    blocks: u8,
+    block_bytes: u16,
+    mode: Mode,
+}
+
+impl Hdma {
+    pub fn new() -> Self {
+        Self {
+            source: 0,
+            destination: 0,
+            blocks: 0,
+            block_bytes: 0,
+            mode: Mode::Off,
+        }
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            // bit 7 reads 0 while active, after completing the length wraps round to 0x7F
+            HDMA5_ADDRESS => {
+                let length = self.blocks.wrapping_sub(1) & LENGTH_MASK;
+                if self.mode == Mode::Off {
+                    HBLANK_BIT | length
+                } else {
+                    length
+                }
+            }
+            // the address registers are write only
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, byte: u8) {
+        match address {
+            HDMA1_ADDRESS => self.source = (byte as u16) << 8 | self.source & 0x00FF,
+            HDMA2_ADDRESS => self.source = self.source & 0xFF00 | (byte & 0xF0) as u16,
+            HDMA3_ADDRESS => {
+                self.destination =
+                    ((byte as u16) << 8 | self.destination & 0x00FF) & DESTINATION_MASK
+            }
+            HDMA4_ADDRESS => {
+                self.destination = (self.destination & 0xFF00 | byte as u16) & DESTINATION_MASK
+            }
+            _ => panic!("unimplemented write hdma register {:x}", address),
+        }
+    }
+
+    // a write to hdma5. writing with bit 7 clear during an hblank transfer cancels it instead
+    // of starting a new one. with the lcd off there are no hblanks, so an hblank transfer
+    // copies its first block straight away
+    pub fn start(&mut self, byte: u8, lcd_enabled: bool) {
+        if self.mode == Mode::Hblank && byte & HBLANK_BIT == 0 {
+            self.mode = Mode::Off;
+            return;
+        }
+
+        self.blocks = (byte & LENGTH_MASK) + 1;
+        self.mode = if byte & HBLANK_BIT != 0 {
+            Mode::Hblank
+        } else {
+            Mode::General
+        };
+        if !lcd_enabled {
+            self.hblank();
+        }
+    }
+
+    // called when the lcd enters mode 0 on a visible line
+    pub fn hblank(&mut self) {
+        if self.mode == Mode::Hblank && self.block_bytes == 0 {
+            self.block_bytes = BLOCK_SIZE;
+        }
+    }
+
+    pub fn is_transferring(&self) -> bool {
+        self.block_bytes != 0 || self.mode == Mode::General
+    }
+
+    pub fn update(&mut self, bus: &mut dyn Bus) {
+        if self.block_bytes == 0 {
+            if self.mode != Mode::General {
+                return;
+            }
+            self.block_bytes = BLOCK_SIZE;
+        }
+
+        for _ in 0..BYTES_PER_CYCLE {
+            let value = bus.read_byte(self.source);
+            bus.write_byte(VRAM_BEGIN + (self.destination & 0x1FFF), value);
+            self.source = self.source.wrapping_add(1);
+            self.destination = self.destination.wrapping_add(1);
+            self.block_bytes -= 1;
+        }
+
+        if self.block_bytes == 0 {
+            self.blocks -= 1;
+            if self.blocks == 0 {
+                self.mode = Mode::Off;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::bus::MockBus;
+    use mockall::predicate::eq;
+
+    fn hdma_with_addresses(source: u16, destination: u16) -> Hdma {
+        let mut hdma = Hdma::new();
+        hdma.write_register(HDMA1_ADDRESS, (source >> 8) as u8);
+        hdma.write_register(HDMA2_ADDRESS, source as u8);
+        hdma.write_register(HDMA3_ADDRESS, (destination >> 8) as u8);
+        hdma.write_register(HDMA4_ADDRESS, destination as u8);
+        hdma
+    }
+
+    fn copying_bus(bytes: usize) -> MockBus {
+        let mut bus = MockBus::new();
+        bus.expect_read_byte().times(bytes).return_const(0xAB);
+        bus.expect_write_byte().times(bytes).return_const(());
+        bus
+    }
+
+    #[test]
+    fn test_addresses_ignore_low_bits() {
+        let hdma = hdma_with_addresses(0xC12F, 0xFF3F);
+
+        assert_eq!(hdma.source, 0xC120);
+        assert_eq!(hdma.destination, 0x1F30);
+    }
+
+    #[test]
+    fn test_general_transfer() {
+        let mut hdma = hdma_with_addresses(0xC000, 0x8800);
+        let mut bus = copying_bus(32);
+        hdma.start(0x01, true);
+
+        let mut cycles = 0;
+        while hdma.is_transferring() {
+            hdma.update(&mut bus);
+            cycles += 1;
+        }
+
+        assert_eq!(cycles, 16);
+        assert_eq!(hdma.source, 0xC020);
+        assert_eq!(hdma.destination, 0x0820);
+        assert_eq!(hdma.read_register(HDMA5_ADDRESS), 0xFF);
+    }
+
+    #[test]
+    fn test_copies_into_vram() {
+        let mut hdma = hdma_with_addresses(0xC000, 0x8010);
+        let mut bus = MockBus::new();
+        bus.expect_read_byte().with(eq(0xC000)).return_const(0x12);
+        bus.expect_read_byte().with(eq(0xC001)).return_const(0x34);
+        bus.expect_write_byte()
+            .once()
+            .with(eq(0x8010), eq(0x12))
+            .return_const(());
+        bus.expect_write_byte()
+            .once()
+            .with(eq(0x8011), eq(0x34))
+            .return_const(());
+        hdma.start(0x00, true);
+
+        hdma.update(&mut bus);
+
+        assert_eq!(hdma.block_bytes, 14);
+    }
+
+    #[test]
+    fn test_hblank_transfer_one_block_per_hblank() {
+        let mut hdma = hdma_with_addresses(0xC000, 0x8000);
+        let mut bus = copying_bus(16);
+        hdma.start(HBLANK_BIT | 0x02, true);
+
+        hdma.update(&mut bus);
+        assert!(!hdma.is_transferring());
+        assert_eq!(hdma.read_register(HDMA5_ADDRESS), 0x02);
+
+        hdma.hblank();
+        for _ in 0..8 {
+            assert!(hdma.is_transferring());
+            hdma.update(&mut bus);
+        }
+
+        assert!(!hdma.is_transferring());
+        assert_eq!(hdma.read_register(HDMA5_ADDRESS), 0x01);
+    }
+
+    #[test]
+    fn test_hblank_transfer_with_lcd_off() {
+        let mut hdma = hdma_with_addresses(0xC000, 0x8000);
+        let mut bus = copying_bus(16);
+        hdma.start(HBLANK_BIT | 0x01, false);
+
+        for _ in 0..8 {
+            assert!(hdma.is_transferring());
+            hdma.update(&mut bus);
+        }
+
+        assert!(!hdma.is_transferring());
+        assert_eq!(hdma.read_register(HDMA5_ADDRESS), 0x00);
+    }
+
+    #[test]
+    fn test_hblank_transfer_cancel() {
+        let mut hdma = hdma_with_addresses(0xC000, 0x8000);
+        hdma.start(HBLANK_BIT | 0x02, true);
+
+        hdma.start(0x00, true);
+        hdma.hblank();
+
+        assert!(!hdma.is_transferring());
+        assert_eq!(hdma.read_register(HDMA5_ADDRESS), HBLANK_BIT | 0x02);
+    }
+}
@@ -17,6 +17,7 @@ pub fn new() -> GameboyCycles {
 
     cycles.push_back(Box::new(|gameboy: &mut Gameboy| {
         gameboy.pc = gameboy.pc.wrapping_add(1);
+        gameboy.stop();
     }));
 
     Box::new(cycles.into_iter())
@@ -156,8 +156,8 @@ impl Interrupts {
     }
 }
 
-const INTERRUPTS_VBLANK_BIT: u8 = 1;
-const INTERRUPTS_LCD_STAT_BIT: u8 = 1 << 1;
+pub(crate) const INTERRUPTS_VBLANK_BIT: u8 = 1;
+pub(crate) const INTERRUPTS_LCD_STAT_BIT: u8 = 1 << 1;
 const INTERRUPTS_TIMER_BIT: u8 = 1 << 2;
 const INTERRUPTS_SERIAL_BIT: u8 = 1 << 3;
 const INTERRUPTS_JOYPAD_BIT: u8 = 1 << 4;
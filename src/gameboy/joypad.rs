@@ -1,8 +1,9 @@
-use crate::emu::controller::Controller;
+use super::buttons::Buttons;
 
 pub const JOYPAD_ADDRESS: u16 = 0xFF00;
 const ACTION_BIT: u8 = 1 << 4;
 const DIRECTIONS_BIT: u8 = 1 << 5;
+const PLAYER_ID: u8 = 0x0F;
 
 #[derive(PartialEq)]
 enum Selected {
@@ -15,6 +16,9 @@ pub struct Joypad {
     directions: u8,
     actions: u8,
     selected: Selected,
+    players: u8,
+    player: u8,
+    previous: u8,
 }
 
 impl Joypad {
@@ -23,18 +27,36 @@ impl Joypad {
             directions: 0xFF,
             actions: 0xFF,
             selected: Selected::None,
+            players: 1,
+            player: 0,
+            previous: 0xFF,
         }
     }
 
+    // only player 1 has a controller connected, the others never press anything
     pub fn read(&mut self) -> u8 {
         match self.selected {
+            _ if self.player != 0 && self.selected != Selected::None => 0xFF,
             Selected::Actions => self.actions,
             Selected::Directions => self.directions,
-            Selected::None => 0xFF,
+            Selected::None => !(self.player & PLAYER_ID),
+        }
+    }
+
+    // sgb multiplayer (MLT_REQ), with neither group selected the low bits read
+    // back the current player which advances each time P15 goes high
+    pub fn set_players(&mut self, players: u8) {
+        if players != self.players {
+            self.players = players;
+            self.player = 0;
         }
     }
 
     pub fn select(&mut self, byte: u8) {
+        if self.players > 1 && self.previous & DIRECTIONS_BIT == 0 && byte & DIRECTIONS_BIT != 0 {
+            self.player = (self.player + 1) % self.players;
+        }
+        self.previous = byte;
         self.selected = match byte {
             ACTION_BIT => Selected::Actions,
             DIRECTIONS_BIT => Selected::Directions,
@@ -42,16 +64,14 @@ impl Joypad {
         }
     }
 
-    pub fn update(&mut self, controller: &Box<dyn Controller>) {
-        self.actions = controller.actions();
-        self.directions = controller.directions();
+    pub fn update(&mut self, buttons: &Buttons) {
+        self.actions = buttons.actions();
+        self.directions = buttons.directions();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::emu::controller::MockController;
-
     use super::*;
 
     #[test]
@@ -82,20 +102,38 @@ mod tests {
         assert!(joypad.read() == 0);
     }
 
+    #[test]
+    fn test_multiplayer() {
+        let mut joypad = Joypad::new();
+        joypad.actions = 0;
+        joypad.set_players(2);
+
+        assert_eq!(joypad.read(), 0xFF);
+        joypad.select(ACTION_BIT);
+        assert_eq!(joypad.read(), 0);
+        joypad.select(ACTION_BIT | DIRECTIONS_BIT);
+        assert_eq!(joypad.read(), 0xFE);
+        joypad.select(ACTION_BIT);
+        assert_eq!(joypad.read(), 0xFF);
+        joypad.select(ACTION_BIT | DIRECTIONS_BIT);
+        assert_eq!(joypad.read(), 0xFF);
+    }
+
     #[test]
     fn test_update() {
-        let mut mock = MockController::new();
-        mock.expect_actions().once().return_const(0);
-        mock.expect_directions().once().return_const(0);
-        let controller: Box<dyn Controller> = Box::new(mock);
+        let buttons = Buttons {
+            a: true,
+            up: true,
+            ..Default::default()
+        };
         let mut joypad = Joypad::new();
 
         assert!(joypad.directions == 0xFF);
         assert!(joypad.actions == 0xFF);
 
-        joypad.update(&controller);
+        joypad.update(&buttons);
 
-        assert!(joypad.directions == 0);
-        assert!(joypad.actions == 0);
+        assert!(joypad.directions == 0b1111_1011);
+        assert!(joypad.actions == 0b1111_1110);
     }
 }
@@ -1,22 +1,43 @@
+mod apu;
 mod bios;
 mod bus;
+mod buttons;
+mod color;
 mod dma;
 mod gpu;
+mod hdma;
 mod instructions;
 mod interrupts;
 mod joypad;
 mod registers;
+mod sgb;
+mod speed;
 mod timer;
+mod wram;
+
+pub use self::apu::{Mixer, Sample, Stems};
+pub use self::buttons::Buttons;
+pub use self::color::Color;
+pub use self::gpu::{
+    compatibility_scheme, manual_scheme, ColorScheme, PpuState, Shades, Sprite, TileAttributes,
+    TILE_DATA_TILES,
+};
 
 use self::bus::{new_address_bus, Bus};
-use crate::emu::{controller::Controller, display::Display, rom::Rom};
+use crate::emu::{display::Display, rom::Rom};
 use instructions::*;
 use registers::*;
+use std::time::Duration;
 
 type GameboyCycle = Box<dyn FnOnce(&mut Gameboy)>;
 type GameboyCycles = Box<dyn ExactSizeIterator<Item = GameboyCycle>>;
 
-const MAX_MCYCLES_PER_FRAME: u32 = 1050000 / 60;
+// 154 lines of 114 cycles. frames end at vblank, this only ends them while the lcd is off
+const MCYCLES_PER_FRAME: u32 = 17556;
+const MCYCLES_PER_SECOND: u64 = 1048576;
+// how long the real thing takes to run one of our frames, for pacing them
+pub const FRAME_DURATION: Duration =
+    Duration::from_nanos(1_000_000_000 * MCYCLES_PER_FRAME as u64 / MCYCLES_PER_SECOND);
 
 pub struct Gameboy {
     registers: Registers,
@@ -24,7 +45,13 @@ pub struct Gameboy {
     pc: u16,
     halted: bool,
     cycles_used: u32,
+    // set by the step that finishes a frame, until the next step
+    frame_finished: bool,
+    // what's left of an instruction when run_frames stopped partway through it
+    pending_cycles: Option<GameboyCycles>,
     trace: bool,
+    cgb_handover: bool,
+    half_cycle: bool,
 }
 
 impl Gameboy {
@@ -37,24 +64,101 @@ impl Gameboy {
             bus,
             pc: 0,
             cycles_used: 0,
+            frame_finished: true,
+            pending_cycles: None,
             halted: false,
             trace: false,
+            cgb_handover: false,
+            half_cycle: false,
         }
     }
 
+    // true once per frame, in double speed too where the frame counter only moves every
+    // other step
     pub fn is_new_frame(&self) -> bool {
-        self.cycles_used == 0
+        self.frame_finished
+    }
+
+    // the boot rom is still running until it unmaps itself
+    pub fn is_booting(&self) -> bool {
+        self.bus.is_bios_mapped()
     }
 
-    pub fn update_joypad(&mut self, controller: &Box<dyn Controller>) {
-        self.bus.update_joypad(controller);
+    pub fn update_joypad(&mut self, buttons: &Buttons) {
+        self.bus.update_joypad(buttons);
     }
 
-    pub fn render_display(&mut self, display: &mut Box<dyn Display>) {
+    pub fn render_display(&mut self, display: &mut dyn Display) {
         self.bus.render_display(display);
     }
 
+    // runs as fast as possible until `frames` more frames have been drawn to the display,
+    // for tests and batch runs without a window
+    pub fn run_frames(&mut self, frames: u32, display: &mut dyn Display) {
+        let mut remaining = frames;
+        while remaining > 0 {
+            let mut cycles = self.cycles();
+            while let Some(cycle) = cycles.next() {
+                self.execute(cycle);
+                if self.is_new_frame() {
+                    self.render_display(display);
+                    display.present();
+                    remaining -= 1;
+                    if remaining == 0 {
+                        // the rest of the instruction runs first thing next time
+                        self.pending_cycles = Some(cycles);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    // samples are only kept once a rate is set, and build up until they're taken
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.bus.set_sample_rate(sample_rate);
+    }
+
+    pub fn take_samples(&mut self) -> Vec<Sample> {
+        self.bus.take_samples()
+    }
+
+    // each channel's samples alongside the mix, for recording them separately
+    pub fn set_stems(&mut self, keep_stems: bool) {
+        self.bus.set_stems(keep_stems);
+    }
+
+    pub fn take_stems(&mut self) -> Vec<Stems> {
+        self.bus.take_stems()
+    }
+
+    pub fn set_mixer(&mut self, mixer: Mixer) {
+        self.bus.set_mixer(mixer);
+    }
+
+    pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        self.bus.set_color_scheme(scheme);
+    }
+
+    pub fn ppu_state(&self) -> PpuState {
+        self.bus.ppu_state()
+    }
+
+    // reads memory as the cpu sees it, for test roms that report their results there
+    pub fn read_memory(&mut self, address: u16) -> u8 {
+        self.bus.read_byte(address)
+    }
+
     pub fn cycles(&mut self) -> GameboyCycles {
+        if let Some(cycles) = self.pending_cycles.take() {
+            return cycles;
+        }
+        // the cpu does nothing while hdma is copying a block
+        if self.bus.is_hdma_transferring() {
+            let stall: GameboyCycle = Box::new(|_: &mut Gameboy| {});
+            return Box::new(std::iter::once(stall));
+        }
+
         let cycles = self.bus.next_interrupt_cycles();
         let has_interrupts = cycles.len() != 0;
 
@@ -65,20 +169,47 @@ impl Gameboy {
         }
     }
 
+    // cpu, timer and oam dma run every step. the ppu side (ppu, hdma, apu and frame
+    // timing) runs every other step in double speed
     pub fn execute(&mut self, step: GameboyCycle) {
+        self.frame_finished = false;
         step(self);
+        let ppu_cycle = self.is_ppu_cycle();
         self.bus.update_dma();
+        if ppu_cycle {
+            self.bus.update_hdma();
+        }
         self.bus.update_ime();
-        self.bus.update_gpu();
+        if ppu_cycle {
+            self.bus.update_gpu();
+        }
         self.bus.update_timer();
-        self.update_cycles_used();
+        if ppu_cycle {
+            self.bus.update_apu();
+            self.update_cycles_used();
+        }
+    }
+
+    fn is_ppu_cycle(&mut self) -> bool {
+        if !self.bus.is_double_speed() {
+            self.half_cycle = false;
+            return true;
+        }
+        self.half_cycle = !self.half_cycle;
+        !self.half_cycle
+    }
+
+    // only the cgb speed switch is implemented, otherwise stop acts as a nop
+    fn stop(&mut self) {
+        self.bus.switch_speed();
     }
 
     fn update_cycles_used(&mut self) {
         self.cycles_used += 1;
 
-        if self.cycles_used == MAX_MCYCLES_PER_FRAME {
+        if self.bus.is_vblank_start() || self.cycles_used == MCYCLES_PER_FRAME {
             self.cycles_used = 0;
+            self.frame_finished = true;
         }
     }
 
@@ -105,12 +236,22 @@ impl Gameboy {
     }
 
     fn fetch(&mut self) -> GameboyCycles {
+        self.handover_cgb();
         let (instruction_byte, prefixed) = self.fetch_instruction_byte();
         let instruction = self.fetch_instruction(instruction_byte, prefixed);
         self.trace(&instruction);
         instruction.fetch()
     }
 
+    // the dmg boot rom jumps to the cartridge with a = 0x01, cgb games check for the
+    // 0x11 the cgb boot rom leaves there before using any color features
+    fn handover_cgb(&mut self) {
+        if self.cgb_handover && self.pc == 0x0100 {
+            self.registers.a = 0x11;
+            self.cgb_handover = false;
+        }
+    }
+
     fn trace(&mut self, instruction: &Instruction) {
         if self.pc == 0x0100 {
             self.trace = false;
@@ -246,5 +387,95 @@ impl Gameboy {
 
     pub fn load_rom(&mut self, rom: &Rom) {
         self.bus.load_rom(rom);
+        self.cgb_handover = rom.is_cgb();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emu::display::HeadlessDisplay, gameboy::bus::MockBus};
+
+    // a bus in double speed that only counts ppu side steps, with hdma stalling the cpu so
+    // every instruction is a single step
+    fn double_speed_bus(ppu_steps: usize) -> MockBus {
+        let mut bus = MockBus::new();
+        bus.expect_is_double_speed().return_const(true);
+        bus.expect_is_hdma_transferring().return_const(true);
+        bus.expect_update_dma().return_const(());
+        bus.expect_update_hdma().return_const(());
+        bus.expect_update_ime().return_const(());
+        bus.expect_update_timer().return_const(());
+        bus.expect_update_apu().return_const(());
+        bus.expect_update_gpu().times(ppu_steps).return_const(());
+        // the lcd is off, so frames are counted out
+        bus.expect_is_vblank_start().return_const(false);
+        bus
+    }
+
+    // a bus with the lcd on, entering vblank every `period` steps
+    fn vblank_bus(period: u32) -> MockBus {
+        let mut bus = MockBus::new();
+        let mut steps = 0;
+        bus.expect_is_double_speed().return_const(false);
+        bus.expect_update_dma().return_const(());
+        bus.expect_update_hdma().return_const(());
+        bus.expect_update_ime().return_const(());
+        bus.expect_update_timer().return_const(());
+        bus.expect_update_apu().return_const(());
+        bus.expect_update_gpu().return_const(());
+        bus.expect_is_vblank_start().returning(move || {
+            steps += 1;
+            steps % period == 0
+        });
+        bus
+    }
+
+    #[test]
+    fn test_new_frame_once_in_double_speed() {
+        let mut gameboy = Gameboy {
+            bus: Box::new(double_speed_bus(MCYCLES_PER_FRAME as usize * 2)),
+            ..Gameboy::new()
+        };
+
+        let mut frames = 0;
+        for _ in 0..MCYCLES_PER_FRAME * 4 {
+            gameboy.execute(Box::new(|_: &mut Gameboy| {}));
+            if gameboy.is_new_frame() {
+                frames += 1;
+            }
+        }
+        assert_eq!(frames, 2);
+    }
+
+    #[test]
+    fn test_run_frames_in_double_speed() {
+        let mut bus = double_speed_bus(MCYCLES_PER_FRAME as usize);
+        bus.expect_render_display().once().return_const(());
+        let mut gameboy = Gameboy {
+            bus: Box::new(bus),
+            ..Gameboy::new()
+        };
+
+        gameboy.run_frames(1, &mut HeadlessDisplay::new());
+    }
+
+    #[test]
+    fn test_frames_end_at_vblank() {
+        let mut gameboy = Gameboy {
+            bus: Box::new(vblank_bus(1000)),
+            cycles_used: 500,
+            ..Gameboy::new()
+        };
+
+        let mut frames = Vec::new();
+        for step in 1..=MCYCLES_PER_FRAME * 2 {
+            gameboy.execute(Box::new(|_: &mut Gameboy| {}));
+            if gameboy.is_new_frame() {
+                frames.push(step);
+            }
+        }
+        let expected: Vec<u32> = (1..=35).map(|frame| frame * 1000).collect();
+        assert_eq!(frames, expected);
     }
 }
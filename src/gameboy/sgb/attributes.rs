@@ -0,0 +1,237 @@
+pub const ATTRIBUTE_WIDTH: usize = 20;
+pub const ATTRIBUTE_HEIGHT: usize = 18;
+pub const ATTRIBUTE_FILE_SIZE: usize = ATTRIBUTE_WIDTH * ATTRIBUTE_HEIGHT / 4;
+
+const BLOCK_INSIDE_BIT: u8 = 1;
+const BLOCK_LINE_BIT: u8 = 1 << 1;
+const BLOCK_OUTSIDE_BIT: u8 = 1 << 2;
+const BLOCK_DATA_SIZE: usize = 6;
+const LINE_HORIZONTAL_BIT: u8 = 1 << 7;
+const DIVIDE_HORIZONTAL_BIT: u8 = 1 << 6;
+
+// which of the 4 sgb palettes each 8x8 cell of the screen uses
+pub struct AttributeMap {
+    cells: [u8; ATTRIBUTE_WIDTH * ATTRIBUTE_HEIGHT],
+}
+
+impl AttributeMap {
+    pub fn new() -> Self {
+        Self {
+            cells: [0; ATTRIBUTE_WIDTH * ATTRIBUTE_HEIGHT],
+        }
+    }
+
+    pub fn palette(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * ATTRIBUTE_WIDTH + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, palette: u8) {
+        if x < ATTRIBUTE_WIDTH && y < ATTRIBUTE_HEIGHT {
+            self.cells[y * ATTRIBUTE_WIDTH + x] = palette & 0b11;
+        }
+    }
+
+    // ATTR_BLK, rectangles with separate palettes for inside, the border line and outside.
+    // when only inside or outside is given the border line uses the same palette
+    pub fn block(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for set in data[2..].chunks(BLOCK_DATA_SIZE).take(count) {
+            if set.len() < BLOCK_DATA_SIZE {
+                break;
+            }
+            let control = set[0] & 0b111;
+            let inside = set[1] & 0b11;
+            let outside = set[1] >> 4 & 0b11;
+            let line = match control {
+                BLOCK_INSIDE_BIT => Some(inside),
+                BLOCK_OUTSIDE_BIT => Some(outside),
+                control if control & BLOCK_LINE_BIT != 0 => Some(set[1] >> 2 & 0b11),
+                _ => None,
+            };
+            let (x1, y1, x2, y2) = (
+                set[2] as usize,
+                set[3] as usize,
+                set[4] as usize,
+                set[5] as usize,
+            );
+
+            for y in 0..ATTRIBUTE_HEIGHT {
+                for x in 0..ATTRIBUTE_WIDTH {
+                    let within = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                    let on_line = within && (x == x1 || x == x2 || y == y1 || y == y2);
+                    if on_line {
+                        if let Some(line) = line {
+                            self.set(x, y, line);
+                        }
+                    } else if within {
+                        if control & BLOCK_INSIDE_BIT != 0 {
+                            self.set(x, y, inside);
+                        }
+                    } else if control & BLOCK_OUTSIDE_BIT != 0 {
+                        self.set(x, y, outside);
+                    }
+                }
+            }
+        }
+    }
+
+    // ATTR_LIN, whole rows or columns set to a palette
+    pub fn line(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for byte in data[2..].iter().take(count) {
+            let line = (byte & 0b1_1111) as usize;
+            let palette = byte >> 5 & 0b11;
+            if byte & LINE_HORIZONTAL_BIT != 0 {
+                for x in 0..ATTRIBUTE_WIDTH {
+                    self.set(x, line, palette);
+                }
+            } else {
+                for y in 0..ATTRIBUTE_HEIGHT {
+                    self.set(line, y, palette);
+                }
+            }
+        }
+    }
+
+    // ATTR_DIV, splits the screen either side of a row or column
+    pub fn divide(&mut self, data: &[u8]) {
+        let after = data[1] & 0b11;
+        let before = data[1] >> 2 & 0b11;
+        let on = data[1] >> 4 & 0b11;
+        let horizontal = data[1] & DIVIDE_HORIZONTAL_BIT != 0;
+        let split = data[2] as usize;
+
+        for y in 0..ATTRIBUTE_HEIGHT {
+            for x in 0..ATTRIBUTE_WIDTH {
+                let position = if horizontal { y } else { x };
+                let palette = match position.cmp(&split) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on,
+                    std::cmp::Ordering::Greater => after,
+                };
+                self.set(x, y, palette);
+            }
+        }
+    }
+
+    // ATTR_CHR, individual cells from a start position, 4 cells per byte msb first,
+    // written left to right or top to bottom
+    pub fn chr(&mut self, data: &[u8]) {
+        let mut x = data[1] as usize;
+        let mut y = data[2] as usize;
+        let count = u16::from_le_bytes([data[3], data[4]]) as usize;
+        let vertical = data[5] & 1 != 0;
+
+        for index in 0..count.min((data.len() - 6) * 4) {
+            let byte = data[6 + index / 4];
+            let palette = byte >> (6 - (index % 4) * 2) & 0b11;
+            self.set(x, y, palette);
+            if vertical {
+                y += 1;
+                if y == ATTRIBUTE_HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+                if x == ATTRIBUTE_WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    // an attribute file from ATTR_TRN, 4 cells per byte msb first
+    pub fn load(&mut self, file: &[u8]) {
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            *cell = file[index / 4] >> (6 - (index % 4) * 2) & 0b11;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(map: &AttributeMap, y: usize) -> Vec<u8> {
+        (0..ATTRIBUTE_WIDTH).map(|x| map.palette(x, y)).collect()
+    }
+
+    #[test]
+    fn test_block() {
+        let mut map = AttributeMap::new();
+        // inside 1, line 2, outside 3, cells 2,2 - 5,5
+        let data = [0x21, 1, 0b111, 0b11_10_01, 2, 2, 5, 5];
+
+        map.block(&data);
+
+        assert_eq!(map.palette(0, 0), 3);
+        assert_eq!(map.palette(2, 2), 2);
+        assert_eq!(map.palette(5, 3), 2);
+        assert_eq!(map.palette(3, 3), 1);
+        assert_eq!(map.palette(6, 3), 3);
+    }
+
+    #[test]
+    fn test_block_inside_only_includes_line() {
+        let mut map = AttributeMap::new();
+        let data = [0x21, 1, 0b001, 0b11_10_01, 2, 2, 5, 5];
+
+        map.block(&data);
+
+        assert_eq!(map.palette(2, 2), 1);
+        assert_eq!(map.palette(3, 3), 1);
+        assert_eq!(map.palette(0, 0), 0);
+    }
+
+    #[test]
+    fn test_line() {
+        let mut map = AttributeMap::new();
+        // row 3 palette 2, column 1 palette 1
+        let data = [0x29, 2, LINE_HORIZONTAL_BIT | 2 << 5 | 3, 1 << 5 | 1];
+
+        map.line(&data);
+
+        assert_eq!(map.palette(5, 3), 2);
+        assert_eq!(map.palette(1, 3), 1);
+        assert_eq!(map.palette(1, 10), 1);
+        assert_eq!(map.palette(5, 4), 0);
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut map = AttributeMap::new();
+        // vertical split at column 10, left 1, on 2, right 3
+        let data = [0x31, 0b0_10_01_11, 10];
+
+        map.divide(&data);
+
+        assert_eq!(row(&map, 0)[9..12], [1, 2, 3]);
+        assert_eq!(row(&map, 17)[0], 1);
+    }
+
+    #[test]
+    fn test_chr_wraps_rows() {
+        let mut map = AttributeMap::new();
+        let data = [0x39, 18, 0, 4, 0, 0, 0b01_10_11_01];
+
+        map.chr(&data);
+
+        assert_eq!(row(&map, 0)[18..], [1, 2]);
+        assert_eq!(row(&map, 1)[..2], [3, 1]);
+    }
+
+    #[test]
+    fn test_load() {
+        let mut map = AttributeMap::new();
+        let mut file = [0; ATTRIBUTE_FILE_SIZE];
+        file[0] = 0b00_01_10_11;
+        file[ATTRIBUTE_FILE_SIZE - 1] = 0b11;
+
+        map.load(&file);
+
+        assert_eq!(row(&map, 0)[..4], [0, 1, 2, 3]);
+        assert_eq!(map.palette(19, 17), 3);
+    }
+}
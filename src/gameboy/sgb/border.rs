@@ -0,0 +1,152 @@
+use crate::{
+    emu::display::{BORDER_HEIGHT, BORDER_WIDTH},
+    gameboy::{gpu::rgb555_color, Color},
+};
+
+const TILE_SIZE: usize = 32;
+const TILES: usize = 256;
+const MAP_WIDTH: usize = 32;
+const MAP_HEIGHT: usize = 28;
+const MAP_SIZE: usize = 0x800;
+const PALETTES: usize = 4;
+const PALETTE_SIZE: usize = 32;
+const TILE_INDEX_MASK: u16 = 0xFF;
+const X_FLIP_BIT: u16 = 1 << 14;
+const Y_FLIP_BIT: u16 = 1 << 15;
+
+// snes tiles and map drawn around the game screen. tiles come from CHR_TRN, 128 at a
+// time, the map and border palettes 4 - 7 from PCT_TRN
+pub struct Border {
+    tiles: Vec<u8>,
+    map: Vec<u8>,
+    palettes: Vec<u8>,
+    pub loaded: bool,
+}
+
+impl Border {
+    pub fn new() -> Self {
+        Self {
+            tiles: vec![0; TILES * TILE_SIZE],
+            map: vec![0; MAP_SIZE],
+            palettes: vec![0; PALETTES * PALETTE_SIZE],
+            loaded: false,
+        }
+    }
+
+    pub fn load_tiles(&mut self, upper: bool, data: &[u8]) {
+        let start = if upper { TILES / 2 * TILE_SIZE } else { 0 };
+        let length = TILES / 2 * TILE_SIZE;
+        self.tiles[start..start + length].copy_from_slice(&data[..length]);
+    }
+
+    pub fn load_map(&mut self, data: &[u8]) {
+        self.map.copy_from_slice(&data[..MAP_SIZE]);
+        self.palettes
+            .copy_from_slice(&data[MAP_SIZE..MAP_SIZE + PALETTES * PALETTE_SIZE]);
+        self.loaded = true;
+    }
+
+    // transparent pixels show the backdrop color
+    pub fn pixels(&self, backdrop: Color) -> Vec<Color> {
+        let mut pixels = vec![backdrop; BORDER_WIDTH as usize * BORDER_HEIGHT as usize];
+        for map_y in 0..MAP_HEIGHT {
+            for map_x in 0..MAP_WIDTH {
+                let offset = (map_y * MAP_WIDTH + map_x) * 2;
+                let entry = u16::from_le_bytes([self.map[offset], self.map[offset + 1]]);
+                let palette = (entry >> 10) as usize & (PALETTES - 1);
+                for row in 0..8 {
+                    let tile_row = if entry & Y_FLIP_BIT != 0 {
+                        7 - row
+                    } else {
+                        row
+                    };
+                    let indexes = self.tile_row((entry & TILE_INDEX_MASK) as usize, tile_row);
+                    for column in 0..8 {
+                        let tile_column = if entry & X_FLIP_BIT != 0 {
+                            7 - column
+                        } else {
+                            column
+                        };
+                        let index = indexes[tile_column];
+                        if index == 0 {
+                            continue;
+                        }
+                        let x = map_x * 8 + column;
+                        let y = map_y * 8 + row;
+                        pixels[y * BORDER_WIDTH as usize + x] = self.color(palette, index);
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    fn color(&self, palette: usize, index: u8) -> Color {
+        let offset = palette * PALETTE_SIZE + index as usize * 2;
+        rgb555_color(u16::from_le_bytes([
+            self.palettes[offset],
+            self.palettes[offset + 1],
+        ]))
+    }
+
+    // 4bpp snes planar format, planes 0 and 1 interleaved in the first 16 bytes
+    // and planes 2 and 3 in the second
+    fn tile_row(&self, tile: usize, row: usize) -> [u8; 8] {
+        let start = tile * TILE_SIZE + row * 2;
+        let planes = [
+            self.tiles[start],
+            self.tiles[start + 1],
+            self.tiles[start + 16],
+            self.tiles[start + 17],
+        ];
+        let mut indexes = [0; 8];
+        for (x, index) in indexes.iter_mut().enumerate() {
+            let bit = 7 - x;
+            for (plane, byte) in planes.iter().enumerate() {
+                *index |= (byte >> bit & 1) << plane;
+            }
+        }
+        indexes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_row_planes() {
+        let mut border = Border::new();
+        let mut data = vec![0; 0x1000];
+        // tile 1 row 2, first pixel 0b1111, second 0b0101, last 0b1000
+        data[32 + 4] = 0b1100_0000;
+        data[32 + 5] = 0b1000_0000;
+        data[32 + 20] = 0b1100_0000;
+        data[32 + 21] = 0b1000_0001;
+
+        border.load_tiles(false, &data);
+
+        assert_eq!(border.tile_row(1, 2), [15, 5, 0, 0, 0, 0, 0, 8]);
+    }
+
+    #[test]
+    fn test_pixels() {
+        let mut border = Border::new();
+        let mut tiles = vec![0; 0x1000];
+        // tile 0x81 row 0 is color 1
+        tiles[32] = 0xFF;
+        let mut map = vec![0; 0x1000];
+        // top left entry uses tile 0x81, palette 5, flipped vertically
+        map[0] = 0x81;
+        map[1] = (Y_FLIP_BIT >> 8) as u8 | 5 << 2;
+        map[MAP_SIZE + PALETTE_SIZE + 2] = 0x1F;
+
+        border.load_tiles(true, &tiles);
+        border.load_map(&map);
+        let pixels = border.pixels(Color::BLACK);
+
+        assert!(border.loaded);
+        assert_eq!(pixels[7 * BORDER_WIDTH as usize], Color::rgb(0xFF, 0, 0));
+        assert_eq!(pixels[0], Color::BLACK);
+    }
+}
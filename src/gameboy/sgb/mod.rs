@@ -0,0 +1,315 @@
+mod attributes;
+mod border;
+mod packet;
+
+use self::{
+    attributes::{AttributeMap, ATTRIBUTE_FILE_SIZE},
+    border::Border,
+    packet::{PacketReceiver, PACKET_SIZE},
+};
+use super::{
+    gpu::{rgb555_color, Gpu},
+    Color,
+};
+use crate::emu::display::{Display, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+const SYSTEM_PALETTES: usize = 512;
+const ATTRIBUTE_FILES: usize = 45;
+const APPLY_ATTRIBUTE_FILE_BIT: u8 = 1 << 7;
+const CANCEL_MASK_BIT: u8 = 1 << 6;
+
+// the sgb boot palette
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mask {
+    Cancel,
+    Freeze,
+    Black,
+    Color0,
+}
+
+// super game boy, receives command packets through the joypad register and colorizes
+// the dmg shades with 4 palettes picked per 8x8 cell, inside an optional border
+pub struct Sgb {
+    receiver: PacketReceiver,
+    command: Vec<u8>,
+    palettes: [[u16; 4]; 4],
+    system_palettes: Vec<[u16; 4]>,
+    attributes: AttributeMap,
+    attribute_files: Vec<u8>,
+    border: Border,
+    border_changed: bool,
+    mask: Mask,
+    players: u8,
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            receiver: PacketReceiver::new(),
+            command: vec![],
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: vec![[0; 4]; SYSTEM_PALETTES],
+            attributes: AttributeMap::new(),
+            attribute_files: vec![0; ATTRIBUTE_FILES * ATTRIBUTE_FILE_SIZE],
+            border: Border::new(),
+            border_changed: false,
+            mask: Mask::Cancel,
+            players: 1,
+        }
+    }
+
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    pub fn write_joypad(&mut self, byte: u8, gpu: &dyn Gpu) {
+        if let Some(packet) = self.receiver.write(byte) {
+            self.receive(&packet, gpu);
+        }
+    }
+
+    // the first packet's header holds the command and how many packets it spans
+    fn receive(&mut self, packet: &[u8; PACKET_SIZE], gpu: &dyn Gpu) {
+        self.command.extend_from_slice(packet);
+        let packets = (self.command[0] & 0b111).max(1) as usize;
+        if self.command.len() < packets * PACKET_SIZE {
+            return;
+        }
+
+        let data = std::mem::take(&mut self.command);
+        self.execute(&data, gpu);
+    }
+
+    fn execute(&mut self, data: &[u8], gpu: &dyn Gpu) {
+        match data[0] >> 3 {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attributes.block(data),
+            ATTR_LIN => self.attributes.line(data),
+            ATTR_DIV => self.attributes.divide(data),
+            ATTR_CHR => self.attributes.chr(data),
+            PAL_SET => self.set_system_palettes(data),
+            PAL_TRN => self.transfer_palettes(&gpu.vram_transfer()),
+            MLT_REQ => self.players = (data[1] & 0b11) + 1,
+            CHR_TRN => {
+                self.border
+                    .load_tiles(data[1] & 1 != 0, &gpu.vram_transfer());
+                self.border_changed = self.border.loaded;
+            }
+            PCT_TRN => {
+                self.border.load_map(&gpu.vram_transfer());
+                self.border_changed = true;
+            }
+            ATTR_TRN => {
+                let transfer = gpu.vram_transfer();
+                let length = ATTRIBUTE_FILES * ATTRIBUTE_FILE_SIZE;
+                self.attribute_files.copy_from_slice(&transfer[..length]);
+            }
+            ATTR_SET => self.apply_attribute_file(data[1]),
+            MASK_EN => self.set_mask(data[1]),
+            _ => (),
+        }
+    }
+
+    // color 0 is shared by every palette, the second palette only sets colors 1 - 3
+    fn set_palettes(&mut self, first: usize, second: usize, data: &[u8]) {
+        let colors: Vec<u16> = data[1..15]
+            .chunks(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        for palette in self.palettes.iter_mut() {
+            palette[0] = colors[0];
+        }
+        self.palettes[first][1..].copy_from_slice(&colors[1..4]);
+        self.palettes[second][1..].copy_from_slice(&colors[4..7]);
+        self.border_changed = self.border.loaded;
+    }
+
+    fn transfer_palettes(&mut self, transfer: &[u8]) {
+        for (palette, bytes) in self.system_palettes.iter_mut().zip(transfer.chunks(8)) {
+            for (color, pair) in palette.iter_mut().zip(bytes.chunks(2)) {
+                *color = u16::from_le_bytes([pair[0], pair[1]]);
+            }
+        }
+    }
+
+    fn set_system_palettes(&mut self, data: &[u8]) {
+        for palette in 0..4 {
+            let offset = 1 + palette * 2;
+            let index = u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+            self.palettes[palette] = self.system_palettes[index % SYSTEM_PALETTES];
+        }
+        let palette0 = self.palettes[0][0];
+        for palette in self.palettes.iter_mut() {
+            palette[0] = palette0;
+        }
+        if data[9] & APPLY_ATTRIBUTE_FILE_BIT != 0 {
+            self.apply_attribute_file(data[9]);
+        }
+        self.border_changed = self.border.loaded;
+    }
+
+    fn apply_attribute_file(&mut self, byte: u8) {
+        let file = (byte & 0b11_1111) as usize;
+        if file < ATTRIBUTE_FILES {
+            let start = file * ATTRIBUTE_FILE_SIZE;
+            self.attributes
+                .load(&self.attribute_files[start..start + ATTRIBUTE_FILE_SIZE]);
+        }
+        if byte & CANCEL_MASK_BIT != 0 {
+            self.mask = Mask::Cancel;
+        }
+    }
+
+    fn set_mask(&mut self, byte: u8) {
+        self.mask = match byte & 0b11 {
+            0 => Mask::Cancel,
+            1 => Mask::Freeze,
+            2 => Mask::Black,
+            _ => Mask::Color0,
+        };
+    }
+
+    fn color(&self, x: usize, y: usize, shade: u8) -> Color {
+        let palette = self.attributes.palette(x / 8, y / 8) as usize;
+        rgb555_color(self.palettes[palette][shade as usize])
+    }
+
+    pub fn render_display(&mut self, gpu: &dyn Gpu, display: &mut dyn Display) {
+        if self.border_changed {
+            display.draw_border(&self.border.pixels(rgb555_color(self.palettes[0][0])));
+            self.border_changed = false;
+        }
+        // a frozen screen keeps showing whatever was last drawn
+        if self.mask == Mask::Freeze {
+            return;
+        }
+
+        let shades = match gpu.shades() {
+            Some(shades) => shades,
+            None => {
+                display.off();
+                return;
+            }
+        };
+        for y in 0..VIDEO_HEIGHT {
+            for x in 0..VIDEO_WIDTH {
+                let shade = shades[y as usize * VIDEO_WIDTH as usize + x as usize];
+                let color = match self.mask {
+                    Mask::Cancel | Mask::Freeze => self.color(x as usize, y as usize, shade),
+                    Mask::Black => Color::BLACK,
+                    Mask::Color0 => rgb555_color(self.palettes[0][0]),
+                };
+                display.draw_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gpu::new_ppu;
+
+    fn packet(bytes: &[u8]) -> [u8; PACKET_SIZE] {
+        let mut packet = [0; PACKET_SIZE];
+        packet[..bytes.len()].copy_from_slice(bytes);
+        packet
+    }
+
+    fn send(sgb: &mut Sgb, bytes: &[u8]) {
+        let gpu = new_ppu();
+        for byte in packet::tests::packet_writes(&packet(bytes)) {
+            sgb.write_joypad(byte, gpu.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_pal01() {
+        let mut sgb = Sgb::new();
+        let data = [
+            PAL01 << 3 | 1,
+            0x00,
+            0x00,
+            0x1F,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0xE0,
+            0x03,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+
+        send(&mut sgb, &data);
+
+        assert_eq!(sgb.palettes[0], [0x0000, 0x001F, 0x0000, 0x0000]);
+        assert_eq!(sgb.palettes[1], [0x0000, 0x03E0, 0x0000, 0x0000]);
+        assert_eq!(sgb.palettes[3][0], 0x0000);
+        assert_eq!(sgb.palettes[3][1], DEFAULT_PALETTE[1]);
+    }
+
+    #[test]
+    fn test_colorize_by_cell() {
+        let mut sgb = Sgb::new();
+        sgb.palettes[2] = [0, 0x001F, 0, 0];
+        // column 1 uses palette 2
+        send(&mut sgb, &[ATTR_LIN << 3 | 1, 1, 2 << 5 | 1]);
+
+        assert_eq!(sgb.color(8, 0, 1), Color::rgb(0xFF, 0, 0));
+        assert_eq!(sgb.color(7, 0, 1), rgb555_color(DEFAULT_PALETTE[1]));
+    }
+
+    #[test]
+    fn test_multi_packet_command() {
+        let mut sgb = Sgb::new();
+        let mut first = [0; PACKET_SIZE];
+        first[0] = ATTR_LIN << 3 | 2;
+        first[1] = 15;
+        // 14 horizontal lines giving row 0 palette 1, the 15th comes in the next packet
+        first[2..].copy_from_slice(&[1 << 7 | 1 << 5; 14]);
+
+        send(&mut sgb, &first);
+        assert_eq!(sgb.attributes.palette(0, 0), 0);
+        send(&mut sgb, &[1 << 7 | 3 << 5 | 1]);
+
+        assert_eq!(sgb.attributes.palette(0, 0), 1);
+        assert_eq!(sgb.attributes.palette(0, 1), 3);
+    }
+
+    #[test]
+    fn test_mlt_req_and_mask() {
+        let mut sgb = Sgb::new();
+
+        send(&mut sgb, &[MLT_REQ << 3 | 1, 0x03]);
+        send(&mut sgb, &[MASK_EN << 3 | 1, 0x02]);
+
+        assert_eq!(sgb.players(), 4);
+        assert_eq!(sgb.mask, Mask::Black);
+    }
+}
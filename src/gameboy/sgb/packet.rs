@@ -0,0 +1,122 @@
+pub const PACKET_SIZE: usize = 16;
+const PACKET_BITS: usize = PACKET_SIZE * 8;
+
+const SELECT_MASK: u8 = 0b0011_0000;
+const RESET: u8 = 0b0000_0000;
+const ZERO: u8 = 0b0010_0000; // P14 low
+const ONE: u8 = 0b0001_0000; // P15 low
+const IDLE: u8 = 0b0011_0000;
+
+#[derive(PartialEq, Debug)]
+enum State {
+    Idle,
+    Receiving,
+}
+
+// packets are sent a bit at a time through the joypad select lines. a reset pulse with
+// both lines low starts a packet, then each of the 128 bits (lsb first) is a pulse of
+// P14 (0) or P15 (1) with both lines released in between, ending with a 0 stop bit
+pub struct PacketReceiver {
+    bytes: [u8; PACKET_SIZE],
+    bit: usize,
+    state: State,
+    previous: u8,
+}
+
+impl PacketReceiver {
+    pub fn new() -> Self {
+        Self {
+            bytes: [0; PACKET_SIZE],
+            bit: 0,
+            state: State::Idle,
+            previous: IDLE,
+        }
+    }
+
+    pub fn write(&mut self, byte: u8) -> Option<[u8; PACKET_SIZE]> {
+        let select = byte & SELECT_MASK;
+        let pulse = self.previous == IDLE && select != IDLE;
+        self.previous = select;
+
+        if select == RESET {
+            self.bytes = [0; PACKET_SIZE];
+            self.bit = 0;
+            self.state = State::Receiving;
+            return None;
+        }
+        if !pulse || self.state != State::Receiving {
+            return None;
+        }
+
+        if self.bit == PACKET_BITS {
+            self.state = State::Idle;
+            return match select {
+                ZERO => Some(self.bytes),
+                _ => None,
+            };
+        }
+        if select == ONE {
+            self.bytes[self.bit / 8] |= 1 << (self.bit % 8);
+        }
+        self.bit += 1;
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // the joypad writes a game makes to send a packet
+    pub fn packet_writes(bytes: &[u8; PACKET_SIZE]) -> Vec<u8> {
+        let mut writes = vec![RESET, IDLE];
+        for bit in 0..PACKET_BITS {
+            let set = bytes[bit / 8] >> (bit % 8) & 1 == 1;
+            writes.push(if set { ONE } else { ZERO });
+            writes.push(IDLE);
+        }
+        writes.push(ZERO);
+        writes.push(IDLE);
+        writes
+    }
+
+    #[test]
+    fn test_receive_packet() {
+        let mut receiver = PacketReceiver::new();
+        let mut bytes = [0; PACKET_SIZE];
+        bytes[0] = 0x89;
+        bytes[15] = 0xA5;
+
+        let packets: Vec<[u8; PACKET_SIZE]> = packet_writes(&bytes)
+            .iter()
+            .filter_map(|byte| receiver.write(*byte))
+            .collect();
+
+        assert_eq!(packets, vec![bytes]);
+    }
+
+    #[test]
+    fn test_ignores_joypad_reads() {
+        let mut receiver = PacketReceiver::new();
+
+        for byte in [ONE, IDLE, ZERO, IDLE].iter().cycle().take(1000) {
+            assert_eq!(receiver.write(*byte), None);
+        }
+        assert_eq!(receiver.state, State::Idle);
+    }
+
+    #[test]
+    fn test_missing_stop_bit() {
+        let mut receiver = PacketReceiver::new();
+        let mut writes = packet_writes(&[0xFF; PACKET_SIZE]);
+        let stop = writes.len() - 2;
+        writes[stop] = ONE;
+
+        let packets = writes
+            .iter()
+            .filter_map(|byte| receiver.write(*byte))
+            .count();
+
+        assert_eq!(packets, 0);
+    }
+}
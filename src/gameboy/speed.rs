@@ -0,0 +1,69 @@
+pub const KEY1_ADDRESS: u16 = 0xFF4D;
+
+const CURRENT_SPEED_BIT: u8 = 1 << 7;
+const SWITCH_ARMED_BIT: u8 = 1;
+
+// cgb speed switch. a write to KEY1 arms it and the next STOP switches between
+// normal and double speed, where the cpu side runs twice for every ppu cycle
+pub struct Speed {
+    pub double: bool,
+    armed: bool,
+}
+
+impl Speed {
+    pub fn new() -> Self {
+        Self {
+            double: false,
+            armed: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        let current = if self.double { CURRENT_SPEED_BIT } else { 0 };
+        let armed = if self.armed { SWITCH_ARMED_BIT } else { 0 };
+        current | 0b0111_1110 | armed
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        self.armed = byte & SWITCH_ARMED_BIT != 0;
+    }
+
+    // returns whether the speed changed
+    pub fn switch(&mut self) -> bool {
+        if !self.armed {
+            return false;
+        }
+        self.armed = false;
+        self.double = !self.double;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_needs_arming() {
+        let mut speed = Speed::new();
+
+        assert!(!speed.switch());
+        assert!(!speed.double);
+        assert_eq!(speed.read(), 0x7E);
+    }
+
+    #[test]
+    fn test_switch() {
+        let mut speed = Speed::new();
+        speed.write(0x01);
+        assert_eq!(speed.read(), 0x7F);
+
+        assert!(speed.switch());
+        assert!(speed.double);
+        assert_eq!(speed.read(), 0xFE);
+
+        speed.write(0x01);
+        assert!(speed.switch());
+        assert!(!speed.double);
+    }
+}
@@ -7,7 +7,11 @@ const TIMER_REGISTER_ADDRESSES: [u16; 4] = [TIMA_ADDRESS, TMA_ADDRESS, TAC_ADDRE
 const TIMA_ADDRESS: u16 = 0xFF05;
 const TMA_ADDRESS: u16 = 0xFF06;
 const TAC_ADDRESS: u16 = 0xFF07;
-const DIV_ADDRESS: u16 = 0xFF04;
+pub const DIV_ADDRESS: u16 = 0xFF04;
+// the apu's frame sequencer is clocked each time DIV bit 4 falls, bit 5 in double speed,
+// as bits 12 and 13 of the full counter
+const DIV_APU_BIT: u16 = 1 << 12;
+const DIV_APU_BIT_DOUBLE_SPEED: u16 = 1 << 13;
 
 pub struct Timer {
     reloaded: bool,
@@ -16,6 +20,8 @@ pub struct Timer {
     counter: u8,           // TIMA
     modulo: u8,            // TMA
     control: TimerControl, // TAC
+    pub double_speed: bool,
+    div_apu_events: u8,
 }
 
 impl Timer {
@@ -34,9 +40,16 @@ impl Timer {
             counter,
             modulo,
             control,
+            double_speed: false,
+            div_apu_events: 0,
         }
     }
 
+    // falling edges of the apu's DIV bit since last asked, writes to DIV included
+    pub fn take_div_apu_events(&mut self) -> u8 {
+        std::mem::take(&mut self.div_apu_events)
+    }
+
     pub fn handles(&self, address: u16) -> bool {
         TIMER_REGISTER_ADDRESSES.contains(&address)
     }
@@ -62,6 +75,7 @@ impl Timer {
     }
 
     pub fn update(&mut self, interrupt_handler: &mut InterruptHandler) {
+        let div = self.div;
         self.reloaded = false;
         if self.overflowed {
             self.div = self.div.wrapping_add(4);
@@ -78,6 +92,7 @@ impl Timer {
         } else {
             self.div = self.div.wrapping_add(4);
         }
+        self.count_div_apu_event(div);
     }
 
     fn write_tima(&mut self, byte: u8) {
@@ -107,10 +122,23 @@ impl Timer {
     }
 
     fn reset_div(&mut self) {
+        let div = self.div;
         if self.control.is_frequency_bit_set(self.div) {
             self.inc();
         }
         self.div = 0;
+        self.count_div_apu_event(div);
+    }
+
+    fn count_div_apu_event(&mut self, old_div: u16) {
+        let bit = if self.double_speed {
+            DIV_APU_BIT_DOUBLE_SPEED
+        } else {
+            DIV_APU_BIT
+        };
+        if old_div & bit != 0 && self.div & bit == 0 {
+            self.div_apu_events += 1;
+        }
     }
 
     fn inc(&mut self) {
@@ -121,3 +149,40 @@ impl Timer {
         self.control.is_frequency_bit_set(self.div)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(timer: &mut Timer, cycles: u32) {
+        let mut interrupt_handler = InterruptHandler::new();
+        (0..cycles).for_each(|_| timer.update(&mut interrupt_handler));
+    }
+
+    #[test]
+    fn test_div_apu_events() {
+        let mut timer = Timer::new();
+        update(&mut timer, 2048);
+        assert_eq!(timer.take_div_apu_events(), 1);
+        assert_eq!(timer.take_div_apu_events(), 0);
+    }
+
+    #[test]
+    fn test_div_apu_events_double_speed() {
+        let mut timer = Timer::new();
+        timer.double_speed = true;
+        update(&mut timer, 2048);
+        assert_eq!(timer.take_div_apu_events(), 0);
+        update(&mut timer, 2048);
+        assert_eq!(timer.take_div_apu_events(), 1);
+    }
+
+    #[test]
+    fn test_div_write_while_bit_set() {
+        let mut timer = Timer::new();
+        update(&mut timer, 1024);
+        timer.write_register(DIV_ADDRESS, 0);
+
+        assert_eq!(timer.take_div_apu_events(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+pub const WRAM_BEGIN: u16 = 0xC000;
+pub const WRAM_END: u16 = 0xDFFF;
+pub const ECHO_BEGIN: u16 = 0xE000;
+pub const ECHO_END: u16 = 0xFDFF;
+pub const SVBK_ADDRESS: u16 = 0xFF70;
+
+const BANK_SIZE: usize = 0x1000;
+const BANKS: usize = 8;
+const BANK_MASK: u8 = 0b111;
+
+// 0xC000 - 0xCFFF is always bank 0, 0xD000 - 0xDFFF is switched between banks 1 - 7
+// through SVBK on cgb. dmg only ever sees bank 1. echo ram mirrors 0xC000 - 0xDDFF
+pub struct Wram {
+    data: [u8; BANK_SIZE * BANKS],
+    svbk: u8,
+}
+
+impl Wram {
+    pub fn new() -> Self {
+        Self {
+            data: [0; BANK_SIZE * BANKS],
+            svbk: 0,
+        }
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        self.data[self.offset(address)]
+    }
+
+    pub fn write(&mut self, address: u16, byte: u8) {
+        let offset = self.offset(address);
+        self.data[offset] = byte;
+    }
+
+    pub fn read_svbk(&self) -> u8 {
+        0b1111_1000 | self.svbk
+    }
+
+    pub fn write_svbk(&mut self, byte: u8) {
+        self.svbk = byte & BANK_MASK;
+    }
+
+    // selecting bank 0 gives bank 1
+    fn bank(&self) -> usize {
+        self.svbk.max(1) as usize
+    }
+
+    fn offset(&self, address: u16) -> usize {
+        let offset = (address - WRAM_BEGIN) as usize & 0x1FFF;
+        if offset < BANK_SIZE {
+            offset
+        } else {
+            self.bank() * BANK_SIZE + offset - BANK_SIZE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_bank() {
+        let mut wram = Wram::new();
+        wram.write(0xD000, 0x11);
+        wram.write_svbk(2);
+        wram.write(0xD000, 0x22);
+
+        assert_eq!(wram.read(0xD000), 0x22);
+        wram.write_svbk(1);
+        assert_eq!(wram.read(0xD000), 0x11);
+    }
+
+    #[test]
+    fn test_bank_0_selects_bank_1() {
+        let mut wram = Wram::new();
+        wram.write_svbk(1);
+        wram.write(0xD123, 0x11);
+
+        wram.write_svbk(0);
+
+        assert_eq!(wram.read(0xD123), 0x11);
+        assert_eq!(wram.read_svbk(), 0xF8);
+    }
+
+    #[test]
+    fn test_bank_0_fixed() {
+        let mut wram = Wram::new();
+        wram.write(0xC010, 0x11);
+
+        wram.write_svbk(7);
+
+        assert_eq!(wram.read(0xC010), 0x11);
+        assert_eq!(wram.read_svbk(), 0xFF);
+    }
+
+    #[test]
+    fn test_echo() {
+        let mut wram = Wram::new();
+        wram.write_svbk(3);
+        wram.write(0xD010, 0x11);
+
+        assert_eq!(wram.read(0xF010), 0x11);
+        wram.write(ECHO_BEGIN, 0x22);
+        assert_eq!(wram.read(WRAM_BEGIN), 0x22);
+    }
+}
@@ -1,5 +1,31 @@
-use garlickboy::emu::Emu;
-
-fn main() {
-    Emu::new().run();
-}
+use std::process;
+
+#[cfg(feature = "sdl")]
+use garlickboy::emu::Emu;
+use garlickboy::emu::{
+    headless::{run_blargg, run_headless},
+    options::Options,
+    terminal::run_terminal,
+};
+
+fn main() {
+    let options = Options::parse(std::env::args().skip(1));
+    match options.headless_frames {
+        _ if options.blargg => process::exit(if run_blargg(&options) { 0 } else { 1 }),
+        Some(frames) => run_headless(&options, frames),
+        None if options.terminal => run_terminal(&options),
+        None => run_window(&options),
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn run_window(options: &Options) {
+    Emu::new(options).run();
+}
+
+// headless, terminal and blargg runs still work on machines without libSDL2
+#[cfg(not(feature = "sdl"))]
+fn run_window(_: &Options) {
+    eprintln!("built without the sdl feature, so there's no window. use --headless, --terminal or --blargg");
+    process::exit(1);
+}